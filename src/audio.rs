@@ -1,16 +1,161 @@
+use std::fmt;
+
+use serde::Deserialize;
+
+mod alarm;
+mod tone;
+pub use alarm::AlarmConfig;
+pub use tone::Tone;
+
 #[cfg(all(not(target_arch = "wasm32"), not(target_env = "musl")))]
 mod native;
 #[cfg(all(not(target_arch = "wasm32"), not(target_env = "musl")))]
 pub use native::*;
 
 // libasound is a shared object and cannot be linked staticly
-// so for musl we don't have any other option
+// so for musl the sounds are handed to an external player
 #[cfg(target_env = "musl")]
-mod stub;
+mod external;
 #[cfg(target_env = "musl")]
-pub use stub::*;
+pub use external::*;
 
 #[cfg(target_arch = "wasm32")]
 mod web;
 #[cfg(target_arch = "wasm32")]
 pub use web::*;
+
+/// The alarm, `None` being the bundled one.
+pub type Param = Option<SoundSource>;
+
+#[cfg(not(target_arch = "wasm32"))]
+pub type FileRef = std::path::PathBuf;
+/// URL of the file.
+#[cfg(target_arch = "wasm32")]
+pub type FileRef = String;
+
+/// Either a file, written as a string, or a tone, written as a table such as
+/// `{ tone = "chime", frequency = 880, duration = "2s" }`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SoundSource {
+    File(FileRef),
+    Tone(Tone),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Sound {
+    WorkComplete,
+    BreakComplete,
+    LongBreakComplete,
+    Click,
+}
+
+/// Sounds for particular events, the alarms default to `audio_param` and the click to silence.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct Sounds {
+    pub work_complete: Option<SoundSource>,
+    pub break_complete: Option<SoundSource>,
+    /// Played instead of `break_complete` when a long break runs out.
+    pub long_break: Option<SoundSource>,
+    /// Short sound played when the timer starts or pauses.
+    pub click: Option<SoundSource>,
+}
+
+impl Sound {
+    pub const ALL: [Sound; 4] = [
+        Sound::WorkComplete,
+        Sound::BreakComplete,
+        Sound::LongBreakComplete,
+        Sound::Click,
+    ];
+
+    /// Key in the `[sounds]` table.
+    pub fn key(self) -> &'static str {
+        match self {
+            Sound::WorkComplete => "work_complete",
+            Sound::BreakComplete => "break_complete",
+            Sound::LongBreakComplete => "long_break",
+            Sound::Click => "click",
+        }
+    }
+}
+
+/// A sound looped in the background while working, on its own channel next to the alarms.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct AmbientConfig {
+    pub sound: Option<SoundSource>,
+    /// Relative to the overall volume.
+    pub volume: f32,
+    /// Keeps playing during breaks.
+    pub during_break: bool,
+}
+
+impl Default for AmbientConfig {
+    fn default() -> Self {
+        Self {
+            sound: None,
+            volume: 0.5,
+            during_break: false,
+        }
+    }
+}
+
+impl Sounds {
+    pub fn get(&self, sound: Sound) -> Option<&SoundSource> {
+        match sound {
+            Sound::WorkComplete => &self.work_complete,
+            Sound::BreakComplete => &self.break_complete,
+            Sound::LongBreakComplete => &self.long_break,
+            Sound::Click => &self.click,
+        }
+        .as_ref()
+    }
+}
+
+/// Catches a missing alarm file when the config is loaded rather than when the alarm should ring.
+pub fn check_param(param: &Param) -> Result<(), String> {
+    match param {
+        Some(source) => check_source(source),
+        None => Ok(()),
+    }
+}
+
+pub fn check_source(source: &SoundSource) -> Result<(), String> {
+    match source {
+        #[cfg(not(target_arch = "wasm32"))]
+        SoundSource::File(path) if !path.is_file() => {
+            Err(format!("no such file `{}`", path.display()))
+        }
+        SoundSource::File(_) => Ok(()),
+        SoundSource::Tone(tone) => tone.check(),
+    }
+}
+
+impl<'de> Deserialize<'de> for SoundSource {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct Visitor;
+
+        impl<'de> serde::de::Visitor<'de> for Visitor {
+            type Value = SoundSource;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("a file or a tone table")
+            }
+
+            fn visit_str<E: serde::de::Error>(self, s: &str) -> Result<SoundSource, E> {
+                Ok(SoundSource::File(s.into()))
+            }
+
+            fn visit_map<A: serde::de::MapAccess<'de>>(
+                self,
+                map: A,
+            ) -> Result<SoundSource, A::Error> {
+                let deserializer = serde::de::value::MapAccessDeserializer::new(map);
+                Tone::deserialize(deserializer).map(SoundSource::Tone)
+            }
+        }
+
+        deserializer.deserialize_any(Visitor)
+    }
+}
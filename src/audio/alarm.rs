@@ -0,0 +1,128 @@
+use std::fmt;
+use std::time::Duration;
+
+use serde::Deserialize;
+
+use crate::Instant;
+
+/// How an alarm plays once an interval runs out.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct AlarmConfig {
+    pub repeat: Repeat,
+    /// Silence between two plays.
+    #[serde(with = "humantime_serde")]
+    pub interval: Duration,
+    /// Time the volume takes to rise from silence to full.
+    #[serde(with = "humantime_serde")]
+    pub fade_in: Duration,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Repeat {
+    /// Number of plays, the first one included.
+    Times(u32),
+    /// Until the alarm is silenced, or the timer started, stopped or paused.
+    UntilStopped,
+}
+
+/// An alarm that may still repeat or fade in, polled by the backend every `TICK` while it lasts.
+pub struct Ringing {
+    /// Plays left after the current one, `None` if it repeats until stopped.
+    remaining: Option<u32>,
+    started: Instant,
+    ended_at: Option<Instant>,
+}
+
+pub enum Step {
+    Wait,
+    Replay,
+    Done,
+}
+
+impl Default for AlarmConfig {
+    fn default() -> Self {
+        Self {
+            repeat: Repeat::Times(1),
+            interval: Duration::from_secs(5),
+            fade_in: Duration::ZERO,
+        }
+    }
+}
+
+impl Ringing {
+    pub const TICK: Duration = Duration::from_millis(50);
+
+    pub fn new(config: &AlarmConfig) -> Self {
+        Self {
+            remaining: match config.repeat {
+                Repeat::Times(n) => Some(n.saturating_sub(1)),
+                Repeat::UntilStopped => None,
+            },
+            started: Instant::now(),
+            ended_at: None,
+        }
+    }
+
+    /// Factor of the configured volume, the fade carries on across repeats.
+    pub fn fade(&self, config: &AlarmConfig) -> f32 {
+        if config.fade_in.is_zero() {
+            return 1.0;
+        }
+        (self.started.elapsed().as_secs_f32() / config.fade_in.as_secs_f32()).min(1.0)
+    }
+
+    /// Decides what to do next, given whether the sound has played to its end.
+    pub fn step(&mut self, ended: bool, config: &AlarmConfig) -> Step {
+        if !ended {
+            return Step::Wait;
+        }
+        if self.remaining == Some(0) {
+            return Step::Done;
+        }
+        let ended_at = *self.ended_at.get_or_insert_with(Instant::now);
+        if ended_at.elapsed() < config.interval {
+            return Step::Wait;
+        }
+
+        self.ended_at = None;
+        if let Some(remaining) = &mut self.remaining {
+            *remaining -= 1;
+        }
+        Step::Replay
+    }
+}
+
+impl<'de> Deserialize<'de> for Repeat {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct Visitor;
+
+        impl serde::de::Visitor<'_> for Visitor {
+            type Value = Repeat;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("a number of plays or \"until_stopped\"")
+            }
+
+            fn visit_i64<E: serde::de::Error>(self, n: i64) -> Result<Repeat, E> {
+                match u32::try_from(n) {
+                    Ok(n) if n > 0 => Ok(Repeat::Times(n)),
+                    _ => Err(E::custom("must play at least once")),
+                }
+            }
+
+            fn visit_u64<E: serde::de::Error>(self, n: u64) -> Result<Repeat, E> {
+                self.visit_i64(n.try_into().unwrap_or(i64::MAX))
+            }
+
+            fn visit_str<E: serde::de::Error>(self, s: &str) -> Result<Repeat, E> {
+                match s {
+                    "until_stopped" => Ok(Repeat::UntilStopped),
+                    _ => Err(E::invalid_value(serde::de::Unexpected::Str(s), &self)),
+                }
+            }
+        }
+
+        deserializer.deserialize_any(Visitor)
+    }
+}
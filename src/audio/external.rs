@@ -0,0 +1,424 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command as Process};
+use std::sync::mpsc::RecvTimeoutError;
+use std::time::Duration;
+
+use super::alarm::{Ringing, Step};
+use super::{AlarmConfig, AmbientConfig, Param, Sound, SoundSource, Sounds, Tone};
+use crate::Instant;
+
+const DEFAULT_ALARM: &[u8] = include_bytes!("../../res/lofi-alarm-clock.mp3");
+const TONE_SAMPLE_RATE: u32 = 44_100;
+
+/// Tries the players most likely to be around, `exec` so that killing the child stops the sound.
+const DEFAULT_PLAYER: &str = r#"
+command -v mpv >/dev/null && exec mpv --no-video --really-quiet --volume="$FLUYENDO_VOLUME" "$FLUYENDO_SOUND"
+command -v ffplay >/dev/null && exec ffplay -nodisp -autoexit -loglevel quiet -volume "$FLUYENDO_VOLUME" "$FLUYENDO_SOUND"
+command -v pw-play >/dev/null && exec pw-play --volume="$((FLUYENDO_VOLUME / 100)).$(printf %02d $((FLUYENDO_VOLUME % 100)))" "$FLUYENDO_SOUND"
+exec paplay --volume="$((FLUYENDO_VOLUME * 65536 / 100))" "$FLUYENDO_SOUND"
+"#;
+
+/// Players keep the volume they start with, so a fading alarm starts out audible.
+const MIN_FADE: f32 = 0.2;
+/// Least time between two runs of the ambient loop, a player that fails sooner is given up on.
+const AMBIENT_MIN_RUN: Duration = Duration::from_secs(1);
+
+pub struct Controller {
+    sender: std::sync::mpsc::Sender<Command>,
+    /// Joined on drop, so that the files written for the player get removed.
+    thread: Option<std::thread::JoinHandle<()>>,
+}
+
+enum Command {
+    Play(Sound),
+    Stop,
+    ChangeSources(Param, Sounds),
+    ChangeVolume(f32),
+    ChangeAlarm(AlarmConfig),
+    ChangePlayer(Option<String>),
+    ChangeAmbient(AmbientConfig),
+    PlayAmbient(bool),
+    Quit,
+}
+
+impl Controller {
+    pub fn new(path: Param, sounds: Sounds) -> Self {
+        start_audio_thread(path, sounds)
+    }
+
+    fn send(&self, cmd: Command) {
+        if let Err(err) = self.sender.send(cmd) {
+            eprintln!("failed to send cmd to audio thread: {err:?}");
+        }
+    }
+
+    pub fn play(&mut self, sound: Sound) {
+        self.send(Command::Play(sound));
+    }
+
+    pub fn stop(&mut self) {
+        self.send(Command::Stop);
+    }
+
+    pub fn update(&self, path: Param, sounds: Sounds) {
+        self.send(Command::ChangeSources(path, sounds));
+    }
+
+    pub fn set_volume(&self, volume: f32) {
+        self.send(Command::ChangeVolume(volume));
+    }
+
+    pub fn set_alarm(&self, alarm: AlarmConfig) {
+        self.send(Command::ChangeAlarm(alarm));
+    }
+
+    /// Shell command playing `$FLUYENDO_SOUND`, `None` for the built-in one.
+    pub fn set_player(&self, command: Option<String>) {
+        self.send(Command::ChangePlayer(command));
+    }
+
+    pub fn set_ambient(&self, ambient: AmbientConfig) {
+        self.send(Command::ChangeAmbient(ambient));
+    }
+
+    pub fn play_ambient(&self, on: bool) {
+        self.send(Command::PlayAmbient(on));
+    }
+}
+
+impl Drop for Controller {
+    fn drop(&mut self) {
+        let _ = self.sender.send(Command::Quit);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+/// Plays each sound by running a player, since there is no sound library to link statically.
+#[derive(Default)]
+struct Player {
+    command: Option<String>,
+    alarm: Param,
+    sounds: Sounds,
+    volume: f32,
+    alarm_config: AlarmConfig,
+    child: Option<Child>,
+    ringing: Option<(Sound, Ringing)>,
+    /// The built-in alarm once written out, players need a file to open.
+    default_alarm: Option<PathBuf>,
+    /// Tones rendered to files, until the sources change.
+    tones: HashMap<Sound, PathBuf>,
+    ambient: AmbientConfig,
+    ambient_on: bool,
+    /// Run anew each time it exits, players can't be paused from here.
+    ambient_child: Option<Child>,
+    ambient_started: Option<Instant>,
+    /// Set once the player failed right away, until the loop is started or changed again.
+    ambient_failed: bool,
+    ambient_tone: Option<PathBuf>,
+}
+
+impl Player {
+    fn play(&mut self, sound: Sound) -> std::io::Result<()> {
+        self.stop();
+        self.ringing = (sound != Sound::Click).then(|| (sound, Ringing::new(&self.alarm_config)));
+        self.spawn(sound)
+    }
+
+    fn stop(&mut self) {
+        self.ringing = None;
+        stop(&mut self.child);
+    }
+
+    /// Repeats the ringing alarm, which gets louder with each play while it fades in.
+    fn tick(&mut self) -> std::io::Result<()> {
+        self.tick_ambient()?;
+        let Some((sound, ringing)) = &mut self.ringing else {
+            return Ok(());
+        };
+        let ended = match &mut self.child {
+            Some(child) => child.try_wait()?.is_some(),
+            None => true,
+        };
+        match ringing.step(ended, &self.alarm_config) {
+            Step::Wait => Ok(()),
+            Step::Replay => {
+                let sound = *sound;
+                self.spawn(sound)
+            }
+            Step::Done => {
+                self.ringing = None;
+                Ok(())
+            }
+        }
+    }
+
+    fn spawn(&mut self, sound: Sound) -> std::io::Result<()> {
+        let fade = self.ringing.as_ref().map_or(1.0, |(_, ringing)| {
+            ringing.fade(&self.alarm_config).max(MIN_FADE)
+        });
+        let volume = self.volume * fade;
+        let source = match (self.sounds.get(sound), sound) {
+            (Some(source), _) => Some(source.clone()),
+            (None, Sound::Click) => return Ok(()),
+            (None, _) => self.alarm.clone(),
+        };
+        let path = match source {
+            Some(SoundSource::File(path)) => path,
+            Some(SoundSource::Tone(tone)) => self.tone_file(sound, &tone)?,
+            None => self.default_alarm()?,
+        };
+        let Some(child) = self.run(&path, volume)? else {
+            return Ok(());
+        };
+        if let Some(mut old) = self.child.replace(child) {
+            let _ = old.wait();
+        }
+        Ok(())
+    }
+
+    fn run(&self, path: &Path, volume: f32) -> std::io::Result<Option<Child>> {
+        if volume <= 0.0 {
+            return Ok(None);
+        }
+        let command = self.command.as_deref().unwrap_or(DEFAULT_PLAYER);
+        let child = Process::new("sh")
+            .arg("-c")
+            .arg(command)
+            .env("FLUYENDO_SOUND", path)
+            .env(
+                "FLUYENDO_VOLUME",
+                ((volume * 100.0).round() as u32).to_string(),
+            )
+            .spawn()?;
+        Ok(Some(child))
+    }
+
+    fn play_ambient(&mut self, on: bool) -> std::io::Result<()> {
+        self.ambient_on = on;
+        self.ambient_failed = false;
+        if on {
+            self.tick_ambient()
+        } else {
+            stop(&mut self.ambient_child);
+            Ok(())
+        }
+    }
+
+    /// Starts the ambient loop over once the player is done with it.
+    fn tick_ambient(&mut self) -> std::io::Result<()> {
+        if !self.ambient_on || self.ambient_failed {
+            return Ok(());
+        }
+        let is_recent = self
+            .ambient_started
+            .is_some_and(|at| at.elapsed() < AMBIENT_MIN_RUN);
+        if let Some(child) = &mut self.ambient_child {
+            let Some(status) = child.try_wait()? else {
+                return Ok(());
+            };
+            self.ambient_child = None;
+            if is_recent && !status.success() {
+                self.ambient_failed = true;
+                eprintln!("stopped the ambient sound, the player failed with {status}");
+                return Ok(());
+            }
+        }
+        // Or a player that is done at once would be run on every tick.
+        if is_recent {
+            return Ok(());
+        }
+        let path = match self.ambient.sound.clone() {
+            Some(SoundSource::File(path)) => path,
+            Some(SoundSource::Tone(tone)) => match &self.ambient_tone {
+                Some(path) => path.clone(),
+                None => self
+                    .ambient_tone
+                    .insert(write_tone("ambient", &tone)?)
+                    .clone(),
+            },
+            None => return Ok(()),
+        };
+        self.ambient_child = self.run(&path, self.volume * self.ambient.volume)?;
+        if self.ambient_child.is_some() {
+            self.ambient_started = Some(Instant::now());
+        }
+        Ok(())
+    }
+
+    /// Runs the ambient loop anew, since players keep the volume they start with.
+    fn set_volume(&mut self, volume: f32) -> std::io::Result<()> {
+        self.volume = volume;
+        if self.ambient_child.is_none() {
+            return Ok(());
+        }
+        stop(&mut self.ambient_child);
+        self.ambient_started = None;
+        self.tick_ambient()
+    }
+
+    fn change_ambient(&mut self, ambient: AmbientConfig) -> std::io::Result<()> {
+        stop(&mut self.ambient_child);
+        self.ambient = ambient;
+        self.ambient_failed = false;
+        remove(self.ambient_tone.take());
+        self.tick_ambient()
+    }
+
+    fn default_alarm(&mut self) -> std::io::Result<PathBuf> {
+        if let Some(path) = &self.default_alarm {
+            return Ok(path.clone());
+        }
+        let path = sound_dir()?.join(format!("alarm-{}.mp3", std::process::id()));
+        std::fs::write(&path, DEFAULT_ALARM)?;
+        self.default_alarm = Some(path.clone());
+        Ok(path)
+    }
+
+    fn tone_file(&mut self, sound: Sound, tone: &Tone) -> std::io::Result<PathBuf> {
+        if let Some(path) = self.tones.get(&sound) {
+            return Ok(path.clone());
+        }
+        let path = write_tone(sound.key(), tone)?;
+        self.tones.insert(sound, path.clone());
+        Ok(path)
+    }
+
+    fn remove_tones(&mut self) {
+        for (_, path) in self.tones.drain() {
+            remove(Some(path));
+        }
+    }
+
+    /// Stops all sounds and removes the files written for them.
+    fn shut_down(&mut self) {
+        self.stop();
+        stop(&mut self.ambient_child);
+        self.remove_tones();
+        remove(self.ambient_tone.take());
+        remove(self.default_alarm.take());
+    }
+}
+
+fn remove(path: Option<PathBuf>) {
+    if let Some(path) = path {
+        let _ = std::fs::remove_file(path);
+    }
+}
+
+fn stop(child: &mut Option<Child>) {
+    if let Some(mut child) = child.take() {
+        let _ = child.kill();
+        let _ = child.wait();
+    }
+}
+
+/// Where sounds are written out for the player, out of reach of other users.
+fn sound_dir() -> std::io::Result<PathBuf> {
+    let dir = crate::dirs::runtime_dir().ok_or(std::io::ErrorKind::NotFound)?;
+    crate::dirs::create_private_dir(&dir)?;
+    Ok(dir)
+}
+
+fn write_tone(key: &str, tone: &Tone) -> std::io::Result<PathBuf> {
+    let name = format!("{key}-{}.wav", std::process::id());
+    let path = sound_dir()?.join(name);
+    std::fs::write(&path, wav(&tone.render(TONE_SAMPLE_RATE), TONE_SAMPLE_RATE))?;
+    Ok(path)
+}
+
+/// 16 bit mono PCM, which any player can read.
+fn wav(samples: &[f32], sample_rate: u32) -> Vec<u8> {
+    let data_len = samples.len() as u32 * 2;
+    let mut out = Vec::with_capacity(44 + data_len as usize);
+    out.extend_from_slice(b"RIFF");
+    out.extend_from_slice(&(36 + data_len).to_le_bytes());
+    out.extend_from_slice(b"WAVEfmt ");
+    out.extend_from_slice(&16u32.to_le_bytes());
+    // PCM, mono.
+    out.extend_from_slice(&1u16.to_le_bytes());
+    out.extend_from_slice(&1u16.to_le_bytes());
+    out.extend_from_slice(&sample_rate.to_le_bytes());
+    // Bytes per second and per sample, bits per sample.
+    out.extend_from_slice(&(sample_rate * 2).to_le_bytes());
+    out.extend_from_slice(&2u16.to_le_bytes());
+    out.extend_from_slice(&16u16.to_le_bytes());
+    out.extend_from_slice(b"data");
+    out.extend_from_slice(&data_len.to_le_bytes());
+    for sample in samples {
+        let sample = (sample.clamp(-1.0, 1.0) * i16::MAX as f32) as i16;
+        out.extend_from_slice(&sample.to_le_bytes());
+    }
+    out
+}
+
+fn start_audio_thread(audio_path: Param, sounds: Sounds) -> Controller {
+    // Unbounded, a dropped command would leave the audio out of step with the timer.
+    let (sender, rx) = std::sync::mpsc::channel::<Command>();
+
+    let thread = std::thread::spawn(move || {
+        let mut player = Player {
+            alarm: audio_path,
+            sounds,
+            volume: 1.0,
+            ..Default::default()
+        };
+
+        loop {
+            // Only wakes up on its own while there is an alarm to repeat or a loop to restart.
+            let cmd = if player.ringing.is_some() || player.ambient_on {
+                match rx.recv_timeout(Ringing::TICK) {
+                    Ok(c) => Some(c),
+                    Err(RecvTimeoutError::Timeout) => None,
+                    Err(RecvTimeoutError::Disconnected) => break,
+                }
+            } else {
+                match rx.recv() {
+                    Ok(c) => Some(c),
+                    Err(_) => break,
+                }
+            };
+
+            let res = match cmd {
+                Some(Command::Quit) => break,
+                None => player.tick(),
+                Some(Command::Play(sound)) => player.play(sound),
+                Some(Command::Stop) => {
+                    player.stop();
+                    Ok(())
+                }
+                Some(Command::ChangeSources(path, sounds)) => {
+                    player.alarm = path;
+                    player.sounds = sounds;
+                    player.remove_tones();
+                    Ok(())
+                }
+                Some(Command::ChangeVolume(v)) => player.set_volume(v),
+                Some(Command::ChangeAmbient(ambient)) => player.change_ambient(ambient),
+                Some(Command::PlayAmbient(on)) => player.play_ambient(on),
+                Some(Command::ChangeAlarm(alarm)) => {
+                    player.alarm_config = alarm;
+                    Ok(())
+                }
+                Some(Command::ChangePlayer(command)) => {
+                    player.command = command;
+                    player.ambient_failed = false;
+                    Ok(())
+                }
+            };
+
+            if let Err(err) = res {
+                eprintln!("failed to run audio player: {err}");
+            }
+        }
+
+        player.shut_down();
+    });
+
+    Controller {
+        sender,
+        thread: Some(thread),
+    }
+}
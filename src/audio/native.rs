@@ -1,79 +1,633 @@
 use std::{
     borrow::Cow,
-    path::{Path, PathBuf},
+    hash::{Hash, Hasher},
+    path::Path,
+    sync::{
+        mpsc::{RecvTimeoutError, Sender},
+        Arc, Mutex,
+    },
+    time::Duration,
 };
 
+use cpal::traits::{DeviceTrait, HostTrait};
+use cpal::FromSample;
+use iced::futures::channel::mpsc;
+use iced::Subscription;
+use raplay::err::CpalError;
+use raplay::sample_buffer::SampleBufferMut;
+use raplay::source::{DeviceConfig, Source, Symph, VolumeIterator};
+use raplay::{CallbackInfo, Timestamp};
+
+use super::alarm::{Ringing, Step};
+use super::{AlarmConfig, AmbientConfig, Param, Sound, SoundSource, Sounds, Tone};
+use crate::Instant;
+
+#[cfg(test)]
+mod tests;
+
 type Result<T = ()> = std::result::Result<T, raplay::Error>;
 
 const DEFAULT_ALARM: &[u8] = include_bytes!("../../res/lofi-alarm-clock.mp3");
+/// How often a configured device that went missing is looked for again.
+const DEVICE_RETRY: Duration = Duration::from_secs(5);
+
+pub struct Controller {
+    sender: Sender<Command>,
+    warning: Warning,
+}
 
-pub type Param = Option<PathBuf>;
+/// Set while the configured device can't be played on.
+#[derive(Clone, Default)]
+struct Warning(Arc<WarningState>);
 
-pub struct Controller(std::sync::mpsc::SyncSender<Command>);
+#[derive(Default)]
+struct WarningState {
+    text: Mutex<Option<String>>,
+    // Filled in once the app subscribes to the changes.
+    changes: Mutex<Option<mpsc::UnboundedSender<()>>>,
+}
 
 enum Command {
-    Start,
+    Play(Sound),
     Stop,
-    ChangeSource(Option<PathBuf>),
+    ChangeSources(Param, Sounds),
     ChangeVolume(f32),
+    ChangeAlarm(AlarmConfig),
+    ChangeAmbient(AmbientConfig),
+    PlayAmbient(bool),
+    AmbientEnded,
+    ChangeDevice(Option<String>),
+    /// Sent by the sinks opened in the given generation.
+    DeviceLost(u32),
 }
 
 impl Controller {
-    pub fn new(path: Param) -> Self {
-        start_audio_thread(path)
+    pub fn new(path: Param, sounds: Sounds) -> Self {
+        start_audio_thread(path, sounds)
     }
 
     fn send(&self, cmd: Command) {
-        if let Err(err) = self.0.try_send(cmd) {
+        if let Err(err) = self.sender.send(cmd) {
             eprintln!("failed to send cmd to audio thread: {err:?}");
         }
     }
 
-    pub fn start(&mut self) {
-        self.send(Command::Start);
+    pub fn play(&mut self, sound: Sound) {
+        self.send(Command::Play(sound));
     }
 
     pub fn stop(&mut self) {
         self.send(Command::Stop);
     }
 
-    pub fn update(&self, path: Param) {
-        self.send(Command::ChangeSource(path));
+    pub fn update(&self, path: Param, sounds: Sounds) {
+        self.send(Command::ChangeSources(path, sounds));
+    }
+
+    pub fn set_volume(&self, volume: f32) {
+        self.send(Command::ChangeVolume(volume));
+    }
+
+    pub fn set_alarm(&self, alarm: AlarmConfig) {
+        self.send(Command::ChangeAlarm(alarm));
+    }
+
+    pub fn set_ambient(&self, ambient: AmbientConfig) {
+        self.send(Command::ChangeAmbient(ambient));
+    }
+
+    pub fn play_ambient(&self, on: bool) {
+        self.send(Command::PlayAmbient(on));
     }
 
-    pub fn mute(&self) {
-        self.send(Command::ChangeVolume(0.0));
+    /// Output device by name, `None` for the default one.
+    pub fn set_device(&self, device: Option<String>) {
+        self.send(Command::ChangeDevice(device));
     }
 
-    pub fn unmute(&self) {
-        self.send(Command::ChangeVolume(1.0));
+    pub fn warning(&self) -> Option<String> {
+        self.warning.get()
+    }
+
+    /// Fires whenever `warning` changes, which the app has no other way to notice.
+    pub fn warning_changes(&self) -> Subscription<()> {
+        Subscription::run_with(self.warning.clone(), Warning::changes)
     }
 }
 
+impl Warning {
+    fn get(&self) -> Option<String> {
+        self.0.text.lock().unwrap().clone()
+    }
+
+    fn set(&self, text: Option<String>) {
+        let mut current = self.0.text.lock().unwrap();
+        if *current == text {
+            return;
+        }
+        *current = text;
+        if let Some(changes) = &*self.0.changes.lock().unwrap() {
+            let _ = changes.unbounded_send(());
+        }
+    }
+
+    fn changes(&self) -> mpsc::UnboundedReceiver<()> {
+        let (sender, receiver) = mpsc::unbounded();
+        *self.0.changes.lock().unwrap() = Some(sender);
+        receiver
+    }
+}
+
+impl Hash for Warning {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        Arc::as_ptr(&self.0).hash(state);
+    }
+}
+
+/// A decoded source that stays in the cache while the sink plays it,
+/// so playing the same sound again only needs a rewind.
+#[derive(Clone)]
+struct Cached(Arc<Mutex<Box<dyn Source>>>);
+
+/// Plays nothing in the default format of a device.
+///
+/// A sink given a device only opens a stream for a source asking for another format than the
+/// one it has, which tones don't, so this is loaded first for the stream to open.
+struct Silence(DeviceConfig);
+
+impl Source for Silence {
+    fn init(&mut self, _: &DeviceConfig) -> anyhow::Result<()> {
+        Ok(())
+    }
+
+    fn read(&mut self, _: &mut SampleBufferMut) -> (usize, anyhow::Result<()>) {
+        (0, Ok(()))
+    }
+
+    fn preferred_config(&mut self) -> Option<DeviceConfig> {
+        Some(self.0.clone())
+    }
+}
+
+/// The background loop, on a sink of its own so that alarms play over it.
 #[derive(Default)]
+struct Ambient {
+    sink: raplay::Sink,
+    config: AmbientConfig,
+    /// Not shared with the cache, a source can't be on two sinks at once.
+    source: Option<Cached>,
+    on: bool,
+    /// Whether the sink holds `source`, so that it can resume where it paused.
+    loaded: bool,
+}
+
+impl Source for Cached {
+    fn set_err_callback(&mut self, err_callback: &raplay::callback::Callback<raplay::Error>) {
+        self.0.lock().unwrap().set_err_callback(err_callback);
+    }
+
+    fn init(&mut self, info: &DeviceConfig) -> anyhow::Result<()> {
+        self.0.lock().unwrap().init(info)
+    }
+
+    fn read(&mut self, buffer: &mut SampleBufferMut) -> (usize, anyhow::Result<()>) {
+        self.0.lock().unwrap().read(buffer)
+    }
+
+    fn preferred_config(&mut self) -> Option<DeviceConfig> {
+        self.0.lock().unwrap().preferred_config()
+    }
+
+    fn volume(&mut self, volume: VolumeIterator) -> bool {
+        self.0.lock().unwrap().volume(volume)
+    }
+
+    fn seek(&mut self, time: Duration) -> anyhow::Result<Timestamp> {
+        self.0.lock().unwrap().seek(time)
+    }
+
+    fn get_time(&self) -> Option<Timestamp> {
+        self.0.lock().unwrap().get_time()
+    }
+}
+
+/// Plays a tone, rendered once the sample rate of the device is known.
+struct ToneSource {
+    tone: Tone,
+    /// Mono.
+    samples: Vec<f32>,
+    sample_rate: u32,
+    channels: usize,
+    position: usize,
+}
+
+impl Source for ToneSource {
+    fn init(&mut self, info: &DeviceConfig) -> anyhow::Result<()> {
+        if info.sample_rate != self.sample_rate {
+            let time = self.timestamp().current;
+            self.samples = self.tone.render(info.sample_rate);
+            self.sample_rate = info.sample_rate;
+            self.seek(time)?;
+        }
+        self.channels = info.channel_count as usize;
+        Ok(())
+    }
+
+    fn read(&mut self, buffer: &mut SampleBufferMut) -> (usize, anyhow::Result<()>) {
+        let written = match buffer {
+            SampleBufferMut::I8(b) => self.fill(b),
+            SampleBufferMut::I16(b) => self.fill(b),
+            SampleBufferMut::I32(b) => self.fill(b),
+            SampleBufferMut::I64(b) => self.fill(b),
+            SampleBufferMut::U8(b) => self.fill(b),
+            SampleBufferMut::U16(b) => self.fill(b),
+            SampleBufferMut::U32(b) => self.fill(b),
+            SampleBufferMut::U64(b) => self.fill(b),
+            SampleBufferMut::F32(b) => self.fill(b),
+            SampleBufferMut::F64(b) => self.fill(b),
+            _ => return (0, Err(anyhow::anyhow!("unsupported sample format"))),
+        };
+        (written, Ok(()))
+    }
+
+    fn seek(&mut self, time: Duration) -> anyhow::Result<Timestamp> {
+        let position = (time.as_secs_f64() * self.sample_rate as f64) as usize;
+        self.position = position.min(self.samples.len());
+        Ok(self.timestamp())
+    }
+
+    fn get_time(&self) -> Option<Timestamp> {
+        Some(self.timestamp())
+    }
+}
+
+impl ToneSource {
+    fn new(tone: Tone) -> Self {
+        Self {
+            tone,
+            samples: vec![],
+            sample_rate: 0,
+            channels: 1,
+            position: 0,
+        }
+    }
+
+    fn fill<T: FromSample<f32> + Clone>(&mut self, buffer: &mut [T]) -> usize {
+        let frames = buffer.chunks_exact_mut(self.channels.max(1));
+        let mut written = 0;
+        for (frame, &sample) in frames.zip(&self.samples[self.position..]) {
+            frame.fill(T::from_sample_(sample));
+            written += frame.len();
+            self.position += 1;
+        }
+        written
+    }
+
+    fn timestamp(&self) -> Timestamp {
+        let at = |samples: usize| {
+            Duration::from_secs_f64(samples as f64 / self.sample_rate.max(1) as f64)
+        };
+        Timestamp::new(
+            at(self.position),
+            self.tone.duration.max(at(self.samples.len())),
+        )
+    }
+}
+
 struct Player {
     sink: raplay::Sink,
+    alarm: Param,
+    sounds: Sounds,
+    /// Keyed by source, `None` being the built-in alarm.
+    cache: Vec<(Option<SoundSource>, Cached)>,
+    volume: f32,
+    alarm_config: AlarmConfig,
+    ringing: Option<(Sound, Ringing)>,
+    ambient: Ambient,
+    device: Option<String>,
+    /// Bumped whenever the sinks are reopened, so that the errors of former ones are ignored.
+    generation: u32,
+    warning: Warning,
+    /// When the missing device was last looked for.
+    retried_at: Option<Instant>,
+    /// Replaced in tests, which can't rely on the devices of the machine.
+    find_device: fn(&str) -> Result<Option<cpal::Device>>,
+    events: Sender<Command>,
 }
 
 impl Player {
-    fn load(&mut self, p: &Path) -> Result<()> {
-        let buf = std::fs::read(p).map_err(|err| raplay::Error::Other(err.into()))?;
-        self.read_from_buf(Cow::Owned(buf))
+    fn new(events: Sender<Command>, warning: Warning) -> Self {
+        Self {
+            sink: Default::default(),
+            alarm: None,
+            sounds: Default::default(),
+            cache: vec![],
+            volume: 1.0,
+            alarm_config: Default::default(),
+            ringing: None,
+            ambient: Default::default(),
+            device: None,
+            generation: 0,
+            warning,
+            retried_at: None,
+            find_device,
+            events,
+        }
+    }
+
+    /// Reopens both sinks on the configured device, or on the default one if it is missing.
+    fn open_sinks(&mut self) -> Result<()> {
+        self.generation += 1;
+        // Failing to list the devices is no reason to stay silent, the default one may still work.
+        let device = match &self.device {
+            Some(name) => (self.find_device)(name).unwrap_or_else(|err| {
+                eprintln!("failed to look for output device `{name}`: {err:?}");
+                None
+            }),
+            None => None,
+        };
+        self.warning.set(match (&self.device, &device) {
+            (Some(name), None) => Some(format!(
+                "output device `{name}` is not available, playing on the default one"
+            )),
+            _ => None,
+        });
+
+        self.sink = self.open_sink(device.clone())?;
+        self.ambient.sink = self.open_sink(device)?;
+        let ended = self.events.clone();
+        self.ambient.sink.on_callback(Some(move |info| {
+            if let CallbackInfo::SourceEnded = info {
+                let _ = ended.send(Command::AmbientEnded);
+            }
+        }))?;
+        self.ambient.loaded = false;
+        if self.ambient.on {
+            self.ambient.restart()?;
+        }
+        self.apply_volume()
+    }
+
+    fn open_sink(&self, device: Option<cpal::Device>) -> Result<raplay::Sink> {
+        let mut sink = raplay::Sink::default();
+        let events = self.events.clone();
+        let generation = self.generation;
+        sink.on_err_callback(Some(move |err| match err {
+            raplay::Error::Cpal(CpalError::Stream(cpal::StreamError::DeviceNotAvailable)) => {
+                let _ = events.send(Command::DeviceLost(generation));
+            }
+            err => eprintln!("audio error: {err:?}"),
+        }))?;
+        if let Some(device) = device {
+            let config = device.default_output_config()?;
+            sink.set_device(Some(device));
+            sink.load(
+                Silence(DeviceConfig {
+                    channel_count: config.channels().into(),
+                    sample_rate: config.sample_rate().0,
+                    sample_format: config.sample_format(),
+                }),
+                false,
+            )?;
+        }
+        Ok(sink)
     }
 
-    fn load_default(&mut self) -> Result<()> {
-        self.read_from_buf(Cow::Borrowed(DEFAULT_ALARM))
+    fn is_fallen_back(&self) -> bool {
+        self.device.is_some() && self.warning.get().is_some()
     }
 
-    fn read_from_buf(&mut self, buf: Cow<'static, [u8]>) -> Result<()> {
-        let src = raplay::source::Symph::try_new(std::io::Cursor::new(buf), &Default::default())?;
-        self.sink.load(src, false)
+    /// Moves back to the configured device once it is available again.
+    fn retry_device(&mut self) -> Result<()> {
+        let Some(name) = self.device.as_ref().filter(|_| self.is_fallen_back()) else {
+            return Ok(());
+        };
+        if self
+            .retried_at
+            .is_some_and(|at| at.elapsed() < DEVICE_RETRY)
+        {
+            return Ok(());
+        }
+        self.retried_at = Some(Instant::now());
+        if (self.find_device)(name)?.is_some() {
+            self.open_sinks()?;
+        }
+        Ok(())
     }
+
+    /// `None` when woken up by the timeout.
+    fn handle(&mut self, cmd: Option<Command>) -> Result<()> {
+        match cmd {
+            None => self.tick().and_then(|()| self.retry_device()),
+            Some(Command::Play(sound)) => self.play(sound),
+            Some(Command::Stop) => self.stop(),
+            Some(Command::ChangeSources(path, sounds)) => self.change_sources(path, sounds),
+            Some(Command::ChangeVolume(v)) => {
+                self.volume = v;
+                self.apply_volume()
+            }
+            Some(Command::ChangeAlarm(alarm)) => {
+                self.alarm_config = alarm;
+                Ok(())
+            }
+            Some(Command::ChangeAmbient(ambient)) => self
+                .ambient
+                .change(ambient)
+                .and_then(|()| self.apply_volume()),
+            Some(Command::PlayAmbient(on)) => self.ambient.play(on),
+            Some(Command::AmbientEnded) if self.ambient.on => self.ambient.restart(),
+            Some(Command::AmbientEnded) => Ok(()),
+            Some(Command::ChangeDevice(device)) => {
+                self.device = device;
+                self.retried_at = None;
+                self.open_sinks()
+            }
+            Some(Command::DeviceLost(generation)) if generation == self.generation => {
+                self.open_sinks()
+            }
+            Some(Command::DeviceLost(_)) => Ok(()),
+        }
+    }
+
+    fn change_sources(&mut self, alarm: Param, sounds: Sounds) -> Result<()> {
+        self.alarm = alarm;
+        self.sounds = sounds;
+        // Files may have changed along with the config, so nothing is reused.
+        self.cache.clear();
+
+        let sources: Vec<_> = Sound::ALL
+            .iter()
+            .filter_map(|&sound| self.source_of(sound))
+            .collect();
+        let mut res = Ok(());
+        for source in sources {
+            if let Err(err) = self.cached(source) {
+                res = Err(err);
+            }
+        }
+        res
+    }
+
+    /// `None` if the sound is silent, `Some(None)` for the built-in alarm.
+    fn source_of(&self, sound: Sound) -> Option<Option<SoundSource>> {
+        match (self.sounds.get(sound), sound) {
+            (Some(source), _) => Some(Some(source.clone())),
+            (None, Sound::Click) => None,
+            (None, _) => Some(self.alarm.clone()),
+        }
+    }
+
+    fn cached(&mut self, source: Option<SoundSource>) -> Result<Cached> {
+        if let Some((_, cached)) = self.cache.iter().find(|(key, _)| *key == source) {
+            return Ok(cached.clone());
+        }
+        let cached = open(source.as_ref())?;
+        self.cache.push((source, cached.clone()));
+        Ok(cached)
+    }
+
+    fn play(&mut self, sound: Sound) -> Result<()> {
+        // The sound goes to the current sink regardless.
+        if let Err(err) = self.retry_device() {
+            eprintln!("failed to look for output device: {err:?}");
+        }
+        self.ringing = (sound != Sound::Click).then(|| (sound, Ringing::new(&self.alarm_config)));
+        self.apply_volume()?;
+        self.load(sound)
+    }
+
+    fn stop(&mut self) -> Result<()> {
+        self.ringing = None;
+        if self.sink.is_playing().map_or(true, |x| x) {
+            self.sink.pause()?;
+        }
+        self.apply_volume()
+    }
+
+    /// Repeats and fades in the ringing alarm.
+    fn tick(&mut self) -> Result<()> {
+        let Some((sound, ringing)) = &mut self.ringing else {
+            return Ok(());
+        };
+        let ended = matches!(
+            self.sink.get_timestamp(),
+            Err(raplay::Error::NoSourceIsPlaying)
+        );
+        match ringing.step(ended, &self.alarm_config) {
+            Step::Wait => {}
+            Step::Replay => {
+                let sound = *sound;
+                self.load(sound)?;
+            }
+            Step::Done => self.ringing = None,
+        }
+        self.apply_volume()
+    }
+
+    fn apply_volume(&self) -> Result<()> {
+        let fade = self
+            .ringing
+            .as_ref()
+            .map_or(1.0, |(_, ringing)| ringing.fade(&self.alarm_config));
+        self.sink.volume(self.volume * fade)?;
+        self.ambient
+            .sink
+            .volume(self.volume * self.ambient.config.volume)
+    }
+
+    fn load(&mut self, sound: Sound) -> Result<()> {
+        let Some(source) = self.source_of(sound) else {
+            return Ok(());
+        };
+        let mut src = self.cached(source)?;
+        src.seek(Duration::ZERO).map_err(raplay::Error::Other)?;
+        self.sink.load(src, true)
+    }
+}
+
+impl Ambient {
+    fn change(&mut self, config: AmbientConfig) -> Result<()> {
+        self.config = config;
+        self.loaded = false;
+        self.source = None;
+        if let Some(source) = &self.config.sound {
+            self.source = Some(open(Some(source))?);
+        }
+        if self.on {
+            self.restart()
+        } else {
+            Ok(())
+        }
+    }
+
+    fn play(&mut self, on: bool) -> Result<()> {
+        self.on = on;
+        match (on, self.loaded) {
+            (true, true) => self.sink.resume(),
+            (true, false) => self.restart(),
+            (false, _) if self.sink.is_playing().map_or(true, |x| x) => self.sink.pause(),
+            (false, _) => Ok(()),
+        }
+    }
+
+    /// Plays the loop from its start, also once it has played to its end.
+    fn restart(&mut self) -> Result<()> {
+        let Some(source) = &self.source else {
+            return self.sink.pause();
+        };
+        let mut src = source.clone();
+        src.seek(Duration::ZERO).map_err(raplay::Error::Other)?;
+        self.sink.load(src, self.on)?;
+        self.loaded = true;
+        Ok(())
+    }
+}
+
+/// Names of the output devices, with whether each is the default one.
+pub fn output_devices() -> Result<Vec<(String, bool)>> {
+    let host = cpal::default_host();
+    let default = host.default_output_device().and_then(|d| d.name().ok());
+    Ok(host
+        .output_devices()?
+        .filter_map(|d| d.name().ok())
+        .map(|name| {
+            let is_default = default.as_ref() == Some(&name);
+            (name, is_default)
+        })
+        .collect())
 }
 
-fn start_audio_thread(audio_path: Option<PathBuf>) -> Controller {
-    use std::time::Duration;
-    let (sender, rx) = std::sync::mpsc::sync_channel::<Command>(5);
+fn find_device(name: &str) -> Result<Option<cpal::Device>> {
+    Ok(cpal::default_host()
+        .output_devices()?
+        .find(|d| d.name().is_ok_and(|n| n == name)))
+}
+
+/// Decodes or synthesizes the source, `None` being the built-in alarm.
+fn open(source: Option<&SoundSource>) -> Result<Cached> {
+    let src: Box<dyn Source> = match source {
+        Some(SoundSource::Tone(tone)) => Box::new(ToneSource::new(*tone)),
+        Some(SoundSource::File(p)) => Box::new(decode(Cow::Owned(read(p)?))?),
+        None => Box::new(decode(Cow::Borrowed(DEFAULT_ALARM))?),
+    };
+    Ok(Cached(Arc::new(Mutex::new(src))))
+}
+
+fn read(p: &Path) -> Result<Vec<u8>> {
+    std::fs::read(p).map_err(|err| raplay::Error::Other(err.into()))
+}
+
+fn decode(buf: Cow<'static, [u8]>) -> Result<Symph> {
+    Symph::try_new(std::io::Cursor::new(buf), &Default::default())
+}
+
+fn start_audio_thread(audio_path: Param, sounds: Sounds) -> Controller {
+    // Unbounded, a dropped command would leave the audio out of step with the timer.
+    let (sender, rx) = std::sync::mpsc::channel::<Command>();
+
+    let warning = Warning::default();
+    let events = sender.clone();
+    let thread_warning = warning.clone();
 
     std::thread::spawn(move || {
         let handle_res = |res| {
@@ -82,31 +636,35 @@ fn start_audio_thread(audio_path: Option<PathBuf>) -> Controller {
             }
         };
 
-        let mut player = Player::default();
-        let res = if let Some(p) = audio_path {
-            player.load(&p)
-        } else {
-            player.load_default()
-        };
-        handle_res(res);
-
-        while let Ok(c) = rx.recv() {
-            let res = match c {
-                Command::Start => player.sink.play(true),
-                Command::Stop if !player.sink.is_playing().map_or(true, |x| x) => continue,
-                Command::Stop => player
-                    .sink
-                    .pause()
-                    .and_then(|()| player.sink.seek_to(Duration::from_secs(0)))
-                    .map(|_| ()),
-                Command::ChangeSource(None) => player.load_default(),
-                Command::ChangeSource(Some(p)) => player.load(&p),
-                Command::ChangeVolume(v) => player.sink.volume(v),
+        let mut player = Player::new(events, thread_warning);
+        handle_res(player.open_sinks());
+        handle_res(player.change_sources(audio_path, sounds));
+
+        loop {
+            // Only wakes up on its own while there is an alarm to repeat or fade in,
+            // or a missing device to look for.
+            let timeout = if player.ringing.is_some() {
+                Some(Ringing::TICK)
+            } else if player.is_fallen_back() {
+                Some(DEVICE_RETRY)
+            } else {
+                None
+            };
+            let cmd = match timeout {
+                Some(timeout) => match rx.recv_timeout(timeout) {
+                    Ok(c) => Some(c),
+                    Err(RecvTimeoutError::Timeout) => None,
+                    Err(RecvTimeoutError::Disconnected) => break,
+                },
+                None => match rx.recv() {
+                    Ok(c) => Some(c),
+                    Err(_) => break,
+                },
             };
 
-            handle_res(res);
+            handle_res(player.handle(cmd));
         }
     });
 
-    Controller(sender)
+    Controller { sender, warning }
 }
@@ -0,0 +1,59 @@
+use super::*;
+
+const MISSING: &str = "no such output device";
+
+/// Sees no output devices at all.
+fn new_player() -> Player {
+    let (events, _) = std::sync::mpsc::channel();
+    let mut player = Player::new(events, Warning::default());
+    player.find_device = |_| Ok(None);
+    player
+}
+
+#[test]
+fn missing_device_falls_back_with_a_warning() {
+    let mut player = new_player();
+    player
+        .handle(Some(Command::ChangeDevice(Some(MISSING.to_string()))))
+        .unwrap();
+    assert!(player.is_fallen_back());
+    assert!(player.warning.get().unwrap().contains(MISSING));
+
+    player.handle(Some(Command::ChangeDevice(None))).unwrap();
+    assert!(!player.is_fallen_back());
+    assert_eq!(player.warning.get(), None);
+}
+
+#[test]
+fn only_current_sinks_reopen_on_device_loss() {
+    let mut player = new_player();
+    player.handle(Some(Command::ChangeDevice(None))).unwrap();
+    let generation = player.generation;
+
+    player
+        .handle(Some(Command::DeviceLost(generation - 1)))
+        .unwrap();
+    assert_eq!(player.generation, generation);
+
+    player
+        .handle(Some(Command::DeviceLost(generation)))
+        .unwrap();
+    assert_eq!(player.generation, generation + 1);
+}
+
+#[test]
+fn failing_to_list_devices_still_falls_back() {
+    let mut player = new_player();
+    player.find_device = |_| {
+        Err(raplay::Error::Other(
+            std::io::Error::other("no host").into(),
+        ))
+    };
+    player
+        .handle(Some(Command::ChangeDevice(Some(MISSING.to_string()))))
+        .unwrap();
+    assert!(player.is_fallen_back());
+
+    player.retried_at = None;
+    player.handle(Some(Command::Play(Sound::Click))).unwrap();
+}
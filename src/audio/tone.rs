@@ -0,0 +1,95 @@
+use std::time::Duration;
+
+use serde::Deserialize;
+
+/// Fades at the edges of a tone and of each beep, so they start and end without a click.
+pub const RAMP: f32 = 0.01;
+/// A beep followed by as much silence.
+pub const BEEP_PERIOD: f32 = 0.25;
+/// Multiples of the frequency and their amplitudes making up a chime.
+pub const CHIME_PARTIALS: [(f32, f32); 3] = [(1.0, 1.0), (2.0, 0.5), (3.0, 0.25)];
+/// How many times a chime decays by `e` over its duration.
+pub const CHIME_DECAY: f32 = 4.0;
+/// Keeps tones about as loud as the bundled alarm.
+pub const PEAK: f32 = 0.5;
+
+/// A sound synthesized on the fly rather than read from a file.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Tone {
+    #[serde(rename = "tone")]
+    pub shape: Shape,
+    /// In hertz.
+    #[serde(default = "default_frequency")]
+    pub frequency: f32,
+    #[serde(default = "default_duration", with = "humantime_serde")]
+    pub duration: Duration,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Shape {
+    Sine,
+    /// Short pulses of a sine.
+    Beep,
+    /// A few harmonics ringing out like a bell.
+    Chime,
+}
+
+fn default_frequency() -> f32 {
+    880.0
+}
+
+fn default_duration() -> Duration {
+    Duration::from_secs(1)
+}
+
+impl Tone {
+    pub fn check(&self) -> Result<(), String> {
+        if !(20.0..=20_000.0).contains(&self.frequency) {
+            return Err("frequency must be between 20 and 20000 Hz".to_string());
+        }
+        if self.duration.is_zero() || self.duration > Duration::from_secs(60) {
+            return Err("duration must be longer than zero and at most a minute".to_string());
+        }
+        Ok(())
+    }
+
+    /// Mono samples from -1 to 1.
+    pub fn render(&self, sample_rate: u32) -> Vec<f32> {
+        let len = (self.duration.as_secs_f32() * sample_rate as f32) as usize;
+        (0..len)
+            .map(|i| self.sample(i as f32 / sample_rate as f32))
+            .collect()
+    }
+
+    fn sample(&self, t: f32) -> f32 {
+        use std::f32::consts::TAU;
+
+        let duration = self.duration.as_secs_f32();
+        let fade = |t: f32, len: f32| (t / RAMP).min((len - t) / RAMP).clamp(0.0, 1.0);
+        let sine = |multiple: f32| (TAU * self.frequency * multiple * t).sin();
+
+        let value = match self.shape {
+            Shape::Sine => sine(1.0),
+            Shape::Beep => {
+                let t = t % BEEP_PERIOD;
+                let half = BEEP_PERIOD / 2.0;
+                if t < half {
+                    sine(1.0) * fade(t, half)
+                } else {
+                    0.0
+                }
+            }
+            Shape::Chime => {
+                let total: f32 = CHIME_PARTIALS.iter().map(|(_, amplitude)| amplitude).sum();
+                let sum: f32 = CHIME_PARTIALS
+                    .iter()
+                    .map(|&(multiple, amplitude)| sine(multiple) * amplitude)
+                    .sum();
+                sum / total * (-CHIME_DECAY * t / duration).exp()
+            }
+        };
+        value * fade(t, duration) * PEAK
+    }
+}
@@ -1,32 +1,524 @@
-use serde::Deserialize;
-use web_sys::HtmlAudioElement;
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+use std::rc::Rc;
 
-#[derive(Debug, Default, Deserialize, Clone, PartialEq, Eq)]
-pub struct Param;
+use iced::futures::channel::oneshot;
+use wasm_bindgen::closure::Closure;
+use wasm_bindgen::{JsCast, JsValue};
+use web_sys::{
+    AudioBuffer, AudioContext, AudioScheduledSourceNode, File, FileReader, GainNode,
+    HtmlAudioElement, HtmlInputElement, OscillatorNode, Storage, Url,
+};
 
-pub struct Controller(HtmlAudioElement);
+use super::alarm::{Ringing, Step};
+use super::tone::{Shape, BEEP_PERIOD, CHIME_DECAY, CHIME_PARTIALS, PEAK, RAMP};
+use super::{AlarmConfig, AmbientConfig, Param, Sound, SoundSource, Sounds, Tone};
+
+const DEFAULT_ALARM: &str = "lofi-alarm-clock.mp3";
+/// Local storage key of the alarm chosen with `Controller::choose_file`, kept as a data URL.
+const CHOSEN_ALARM_KEY: &str = "fluyendo.alarm";
+
+pub struct Controller {
+    alarm: RefCell<Param>,
+    /// Takes precedence over `alarm`, picked in the browser rather than configured.
+    chosen: RefCell<Option<String>>,
+    sounds: RefCell<Sounds>,
+    /// One element per URL, so each sound is fetched once.
+    elements: RefCell<HashMap<String, HtmlAudioElement>>,
+    playback: Rc<RefCell<Playback>>,
+    /// Interval polling the ringing alarm, cleared once it is over.
+    timer: Rc<Cell<Option<i32>>>,
+    on_tick: Closure<dyn FnMut()>,
+    ambient: RefCell<Ambient>,
+    /// Why the chosen alarm won't be there on the next visit.
+    warning: Rc<RefCell<Option<String>>>,
+}
+
+struct Playback {
+    playing: Option<Playing>,
+    ringing: Option<Ringing>,
+    alarm: AlarmConfig,
+    volume: f32,
+    /// Created with the first tone.
+    context: Option<AudioContext>,
+}
+
+enum Playing {
+    Element(HtmlAudioElement),
+    Synth(Synth),
+}
+
+/// Oscillators sounding a tone, built anew for each play since they only start once.
+struct Synth {
+    tone: Tone,
+    oscillators: Vec<OscillatorNode>,
+    volume: GainNode,
+    ends_at: f64,
+}
+
+/// The background loop, playing alongside the alarms.
+#[derive(Default)]
+struct Ambient {
+    config: AmbientConfig,
+    on: bool,
+    /// Kept while paused, so that a file resumes where it was.
+    element: Option<HtmlAudioElement>,
+    /// A tone rendered once, played by a new node each time since nodes only start once.
+    buffer: Option<AudioBuffer>,
+    node: Option<(AudioScheduledSourceNode, GainNode)>,
+}
 
 impl Controller {
-    pub fn new(_: Param) -> Self {
-        Self(HtmlAudioElement::new_with_src("lofi-alarm-clock.mp3").unwrap())
+    pub fn new(alarm: Param, sounds: Sounds) -> Self {
+        let playback = Rc::new(RefCell::new(Playback {
+            playing: None,
+            ringing: None,
+            alarm: AlarmConfig::default(),
+            volume: 1.0,
+            context: None,
+        }));
+        let timer = Rc::new(Cell::new(None));
+
+        let on_tick = Closure::<dyn FnMut()>::new({
+            let playback = playback.clone();
+            let timer = timer.clone();
+            move || {
+                let mut playback = playback.borrow_mut();
+                playback.tick();
+                if playback.ringing.is_none() {
+                    clear(&timer);
+                }
+            }
+        });
+
+        let chosen = local_storage().and_then(|storage| storage.get_item(CHOSEN_ALARM_KEY).ok()?);
+
+        Self {
+            alarm: RefCell::new(alarm),
+            chosen: RefCell::new(chosen),
+            sounds: RefCell::new(sounds),
+            elements: RefCell::default(),
+            playback,
+            timer,
+            on_tick,
+            ambient: RefCell::default(),
+            warning: Rc::default(),
+        }
     }
 
-    pub fn start(&mut self) {
-        let _ = self.0.play().unwrap();
+    fn element(&self, url: &str) -> HtmlAudioElement {
+        self.elements
+            .borrow_mut()
+            .entry(url.to_string())
+            .or_insert_with(|| HtmlAudioElement::new_with_src(url).unwrap())
+            .clone()
+    }
+
+    pub fn play(&mut self, sound: Sound) {
+        self.stop();
+        let source = match (self.sounds.borrow().get(sound), sound) {
+            (Some(source), _) => source.clone(),
+            (None, Sound::Click) => return,
+            (None, _) => match (self.chosen.borrow().clone(), self.alarm.borrow().clone()) {
+                (Some(url), _) => SoundSource::File(url),
+                (None, Some(source)) => source,
+                (None, None) => SoundSource::File(DEFAULT_ALARM.to_string()),
+            },
+        };
+
+        let mut playback = self.playback.borrow_mut();
+        let playing = match source {
+            SoundSource::File(url) => {
+                let element = self.element(&url);
+                let _ = element.play().unwrap();
+                Playing::Element(element)
+            }
+            SoundSource::Tone(tone) => match playback.synth(tone) {
+                Ok(synth) => Playing::Synth(synth),
+                Err(err) => {
+                    eprintln!("failed to play tone: {err:?}");
+                    return;
+                }
+            },
+        };
+        playback.ringing = (sound != Sound::Click).then(|| Ringing::new(&playback.alarm));
+        playback.playing = Some(playing);
+        playback.apply_volume();
+
+        if playback.ringing.is_some() {
+            let timer = web_sys::window()
+                .unwrap()
+                .set_interval_with_callback_and_timeout_and_arguments_0(
+                    self.on_tick.as_ref().unchecked_ref(),
+                    Ringing::TICK.as_millis() as i32,
+                )
+                .ok();
+            self.timer.set(timer);
+        }
     }
 
     pub fn stop(&mut self) {
-        self.0.pause().unwrap();
-        self.0.set_current_time(0.);
+        clear(&self.timer);
+        let mut playback = self.playback.borrow_mut();
+        playback.ringing = None;
+        if let Some(playing) = playback.playing.take() {
+            playing.stop();
+        }
+    }
+
+    pub fn update(&self, alarm: Param, sounds: Sounds) {
+        *self.alarm.borrow_mut() = alarm;
+        *self.sounds.borrow_mut() = sounds;
+    }
+
+    /// Switches to an alarm returned by `choose_file`, or back to the configured one.
+    pub fn choose_alarm(&self, url: Option<String>) {
+        if url.is_none() {
+            if let Some(storage) = local_storage() {
+                let _ = storage.remove_item(CHOSEN_ALARM_KEY);
+            }
+            *self.warning.borrow_mut() = None;
+        }
+        let previous = self.chosen.replace(url);
+        if let Some(previous) = previous.filter(|url| url.starts_with("blob:")) {
+            self.elements.borrow_mut().remove(&previous);
+            let _ = Url::revoke_object_url(&previous);
+        }
+    }
+
+    pub fn warning(&self) -> Option<String> {
+        self.warning.borrow().clone()
+    }
+
+    /// Asks for an audio file and stores it for later visits, resolving to an object URL of it
+    /// once it is stored.
+    ///
+    /// Never resolves if the dialog is cancelled.
+    pub fn choose_file(&self) -> Option<oneshot::Receiver<String>> {
+        let document = web_sys::window()?.document()?;
+        let input: HtmlInputElement = document.create_element("input").ok()?.dyn_into().ok()?;
+        input.set_type("file");
+        input.set_accept("audio/*");
+
+        let (sender, receiver) = oneshot::channel();
+        let sender = Cell::new(Some(sender));
+        let warning = self.warning.clone();
+        let onchange = Closure::<dyn FnMut()>::new({
+            let input = input.clone();
+            move || {
+                let Some(file) = input.files().and_then(|files| files.get(0)) else {
+                    return;
+                };
+                let (Ok(url), Some(sender)) =
+                    (Url::create_object_url_with_blob(&file), sender.take())
+                else {
+                    return;
+                };
+                let warning = warning.clone();
+                store(&file, move |res| {
+                    *warning.borrow_mut() = res.err();
+                    let _ = sender.send(url);
+                });
+            }
+        });
+        input.set_onchange(Some(onchange.as_ref().unchecked_ref()));
+        onchange.forget();
+        input.click();
+
+        Some(receiver)
+    }
+
+    pub fn has_chosen_alarm(&self) -> bool {
+        self.chosen.borrow().is_some()
+    }
+
+    pub fn set_volume(&self, volume: f32) {
+        let mut playback = self.playback.borrow_mut();
+        playback.volume = volume;
+        playback.apply_volume();
+        self.ambient.borrow().apply_volume(volume);
+    }
+
+    pub fn set_alarm(&self, alarm: AlarmConfig) {
+        self.playback.borrow_mut().alarm = alarm;
+    }
+
+    pub fn set_ambient(&self, config: AmbientConfig) {
+        let mut ambient = self.ambient.borrow_mut();
+        let on = ambient.on;
+        ambient.pause();
+        *ambient = Ambient {
+            config,
+            ..Default::default()
+        };
+        drop(ambient);
+        self.play_ambient(on);
     }
 
-    pub fn update(&self, _: Param) {}
+    pub fn play_ambient(&self, on: bool) {
+        let mut ambient = self.ambient.borrow_mut();
+        if on == ambient.on {
+            return;
+        }
+        ambient.on = on;
+        if !on {
+            ambient.pause();
+            return;
+        }
+        let mut playback = self.playback.borrow_mut();
+        if let Err(err) = ambient.play(&mut playback) {
+            eprintln!("failed to play ambient sound: {err:?}");
+        }
+        ambient.apply_volume(playback.volume);
+    }
+}
+
+impl Playback {
+    /// Repeats and fades in the ringing alarm.
+    fn tick(&mut self) {
+        let (Some(ringing), Some(playing)) = (&mut self.ringing, &self.playing) else {
+            return;
+        };
+        let ended = match playing {
+            Playing::Element(element) => element.ended(),
+            Playing::Synth(synth) => self
+                .context
+                .as_ref()
+                .is_none_or(|context| context.current_time() >= synth.ends_at),
+        };
+        match ringing.step(ended, &self.alarm) {
+            Step::Wait => {}
+            Step::Replay => match playing {
+                Playing::Element(element) => {
+                    element.set_current_time(0.);
+                    let _ = element.play().unwrap();
+                }
+                Playing::Synth(synth) => match self.synth(synth.tone) {
+                    Ok(synth) => self.playing = Some(Playing::Synth(synth)),
+                    Err(err) => eprintln!("failed to play tone: {err:?}"),
+                },
+            },
+            Step::Done => self.ringing = None,
+        }
+        self.apply_volume();
+    }
+
+    fn apply_volume(&self) {
+        let fade = self
+            .ringing
+            .as_ref()
+            .map_or(1.0, |ringing| ringing.fade(&self.alarm));
+        let volume = self.volume * fade;
+        match &self.playing {
+            Some(Playing::Element(element)) => element.set_volume(volume.into()),
+            Some(Playing::Synth(synth)) => synth.volume.gain().set_value(volume),
+            None => {}
+        }
+    }
 
-    pub fn mute(&self) {
-        self.0.set_volume(0.0);
+    fn synth(&mut self, tone: Tone) -> Result<Synth, JsValue> {
+        Synth::start(&self.context()?, tone)
     }
 
-    pub fn unmute(&self) {
-        self.0.set_volume(1.0);
+    fn context(&mut self) -> Result<AudioContext, JsValue> {
+        let context = match &self.context {
+            Some(context) => context.clone(),
+            None => self.context.insert(AudioContext::new()?).clone(),
+        };
+        // A context created before the page was interacted with starts suspended.
+        let _ = context.resume();
+        Ok(context)
+    }
+}
+
+impl Ambient {
+    fn play(&mut self, playback: &mut Playback) -> Result<(), JsValue> {
+        match self.config.sound.clone() {
+            Some(SoundSource::File(url)) => {
+                let element = match &self.element {
+                    Some(element) => element.clone(),
+                    None => {
+                        let element = HtmlAudioElement::new_with_src(&url)?;
+                        element.set_loop(true);
+                        self.element.insert(element).clone()
+                    }
+                };
+                let _ = element.play()?;
+            }
+            Some(SoundSource::Tone(tone)) => {
+                let context = playback.context()?;
+                let buffer = match &self.buffer {
+                    Some(buffer) => buffer.clone(),
+                    None => {
+                        let sample_rate = context.sample_rate();
+                        let samples = tone.render(sample_rate as u32);
+                        let buffer = context.create_buffer(1, samples.len() as u32, sample_rate)?;
+                        buffer.copy_to_channel(&samples, 0)?;
+                        self.buffer.insert(buffer).clone()
+                    }
+                };
+                let volume = context.create_gain()?;
+                volume.connect_with_audio_node(&context.destination())?;
+                let node = context.create_buffer_source()?;
+                node.set_buffer(Some(&buffer));
+                node.set_loop(true);
+                node.connect_with_audio_node(&volume)?;
+                node.start()?;
+                self.node = Some((node.into(), volume));
+            }
+            None => {}
+        }
+        Ok(())
+    }
+
+    fn pause(&mut self) {
+        if let Some(element) = &self.element {
+            let _ = element.pause();
+        }
+        if let Some((node, volume)) = self.node.take() {
+            let _ = node.stop();
+            let _ = volume.disconnect();
+        }
+    }
+
+    fn apply_volume(&self, volume: f32) {
+        let volume = volume * self.config.volume;
+        if let Some(element) = &self.element {
+            element.set_volume(volume.into());
+        }
+        if let Some((_, gain)) = &self.node {
+            gain.gain().set_value(volume);
+        }
+    }
+}
+
+impl Playing {
+    fn stop(self) {
+        match self {
+            Playing::Element(element) => {
+                element.pause().unwrap();
+                element.set_current_time(0.);
+            }
+            Playing::Synth(synth) => {
+                for oscillator in &synth.oscillators {
+                    let _ = oscillator.stop();
+                }
+                let _ = synth.volume.disconnect();
+            }
+        }
+    }
+}
+
+impl Synth {
+    /// Mirrors `Tone::render` with gain automation.
+    fn start(context: &AudioContext, tone: Tone) -> Result<Self, JsValue> {
+        let start = context.current_time();
+        let end = start + tone.duration.as_secs_f64();
+        let ramp = f64::from(RAMP);
+
+        let volume = context.create_gain()?;
+        volume.connect_with_audio_node(&context.destination())?;
+        let envelope = context.create_gain()?;
+        envelope.connect_with_audio_node(&volume)?;
+
+        let gain = envelope.gain();
+        gain.set_value_at_time(0.0, start)?;
+        match tone.shape {
+            Shape::Sine => {
+                gain.linear_ramp_to_value_at_time(PEAK, start + ramp)?;
+                gain.set_value_at_time(PEAK, end - ramp)?;
+            }
+            Shape::Beep => {
+                let period = f64::from(BEEP_PERIOD);
+                let mut at = start;
+                while at < end {
+                    let off = (at + period / 2.0).min(end);
+                    gain.set_value_at_time(0.0, at)?;
+                    gain.linear_ramp_to_value_at_time(PEAK, at + ramp)?;
+                    gain.set_value_at_time(PEAK, off - ramp)?;
+                    gain.linear_ramp_to_value_at_time(0.0, off)?;
+                    at += period;
+                }
+            }
+            Shape::Chime => {
+                gain.linear_ramp_to_value_at_time(PEAK, start + ramp)?;
+                gain.exponential_ramp_to_value_at_time(PEAK * (-CHIME_DECAY).exp(), end - ramp)?;
+            }
+        }
+        gain.linear_ramp_to_value_at_time(0.0, end)?;
+
+        let partials: &[(f32, f32)] = match tone.shape {
+            Shape::Chime => &CHIME_PARTIALS,
+            Shape::Sine | Shape::Beep => &[(1.0, 1.0)],
+        };
+        let total: f32 = partials.iter().map(|(_, amplitude)| amplitude).sum();
+        let mut oscillators = vec![];
+        for &(multiple, amplitude) in partials {
+            let oscillator = context.create_oscillator()?;
+            oscillator.frequency().set_value(tone.frequency * multiple);
+            let level = context.create_gain()?;
+            level.gain().set_value(amplitude / total);
+            oscillator.connect_with_audio_node(&level)?;
+            level.connect_with_audio_node(&envelope)?;
+            oscillator.start()?;
+            oscillator.stop_with_when(end)?;
+            oscillators.push(oscillator);
+        }
+
+        Ok(Self {
+            tone,
+            oscillators,
+            volume,
+            ends_at: end,
+        })
+    }
+}
+
+/// Object URLs don't outlive the page, so the file itself is stored, calling `done` with
+/// whether that worked.
+fn store(file: &File, done: impl FnOnce(Result<(), String>) + 'static) {
+    let Ok(reader) = FileReader::new() else {
+        return done(Err("the chosen alarm can't be remembered".to_string()));
+    };
+    let done = Cell::new(Some(done));
+    let onloadend = Closure::<dyn FnMut()>::new({
+        let reader = reader.clone();
+        move || {
+            let data = reader.result().ok().and_then(|result| result.as_string());
+            let storage = local_storage();
+            let stored = match (data, &storage) {
+                (Some(data), Some(storage)) => storage.set_item(CHOSEN_ALARM_KEY, &data).is_ok(),
+                _ => false,
+            };
+            if let (false, Some(storage)) = (stored, &storage) {
+                // Or the alarm chosen before would come back on the next visit.
+                let _ = storage.remove_item(CHOSEN_ALARM_KEY);
+            }
+            if let Some(done) = done.take() {
+                done(if stored {
+                    Ok(())
+                } else {
+                    Err(
+                        "the chosen alarm is too large to be remembered on the next visit"
+                            .to_string(),
+                    )
+                });
+            }
+        }
+    });
+    reader.set_onloadend(Some(onloadend.as_ref().unchecked_ref()));
+    onloadend.forget();
+    let _ = reader.read_as_data_url(file);
+}
+
+fn local_storage() -> Option<Storage> {
+    web_sys::window()?.local_storage().ok()?
+}
+
+fn clear(timer: &Cell<Option<i32>>) {
+    if let Some(handle) = timer.take() {
+        web_sys::window()
+            .unwrap()
+            .clear_interval_with_handle(handle);
     }
 }
@@ -0,0 +1,113 @@
+use anyhow::{bail, Result};
+
+#[cfg(all(test, not(target_env = "musl")))]
+mod tests;
+
+pub const USAGE: &str = "\
+Usage:
+    fluyendo [--config <path/to/config.toml>] [--headless]
+    fluyendo --list-devices
+    fluyendo ctl <start|stop|pause|reload|cancel-audio|status|watch>
+
+The config is read from the first of these that is set:
+    1. the `--config` argument,
+    2. the `FLUYENDO_CONFIG` environment variable,
+    3. `$XDG_CONFIG_HOME/fluyendo/config.toml` (`~/.config/fluyendo/config.toml`),
+       used only if it exists and watched so it can be created later.
+
+`--headless` runs the timer in the terminal instead of opening a window.
+`--list-devices` prints the audio output devices `output_device` can be set to.
+`ctl watch` prints waybar-compatible JSON lines until killed.";
+
+pub enum Command {
+    Run {
+        config_path: Option<String>,
+        headless: bool,
+    },
+    #[cfg(unix)]
+    Ctl(crate::control::Command),
+    #[cfg(all(not(target_arch = "wasm32"), not(target_env = "musl")))]
+    ListDevices,
+    Help,
+}
+
+pub fn parse(mut args: impl Iterator<Item = String>) -> Result<Command> {
+    let mut config_path = None;
+    let mut headless = false;
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-h" | "--help" => return Ok(Command::Help),
+            "-c" | "--config" => {
+                let Some(path) = args.next() else {
+                    bail!("missing value for `{arg}`\n\n{USAGE}");
+                };
+                config_path = Some(path);
+            }
+            "--headless" => headless = true,
+            #[cfg(all(not(target_arch = "wasm32"), not(target_env = "musl")))]
+            "--list-devices" => return Ok(Command::ListDevices),
+            #[cfg(target_env = "musl")]
+            "--list-devices" => {
+                bail!("static builds play sounds through `player_command`, which picks the device")
+            }
+            "ctl" => return parse_ctl(args),
+            _ => bail!("unexpected argument `{arg}`\n\n{USAGE}"),
+        }
+    }
+
+    Ok(Command::Run {
+        config_path,
+        headless,
+    })
+}
+
+#[cfg(unix)]
+fn parse_ctl(mut args: impl Iterator<Item = String>) -> Result<Command> {
+    let (Some(cmd), None) = (args.next(), args.next()) else {
+        bail!("`ctl` expects exactly one command\n\n{USAGE}");
+    };
+    match crate::control::Command::parse(&cmd) {
+        Some(cmd) => Ok(Command::Ctl(cmd)),
+        None => bail!("unknown command `{cmd}`\n\n{USAGE}"),
+    }
+}
+
+#[cfg(not(unix))]
+fn parse_ctl(_: impl Iterator<Item = String>) -> Result<Command> {
+    bail!("`ctl` is only supported on unix platforms")
+}
+
+#[cfg(all(not(target_arch = "wasm32"), not(target_env = "musl")))]
+pub fn list_devices() -> Result<()> {
+    for (name, is_default) in crate::audio::output_devices()? {
+        if is_default {
+            println!("{name} (default)");
+        } else {
+            println!("{name}");
+        }
+    }
+    Ok(())
+}
+
+#[cfg(unix)]
+pub fn ctl(cmd: crate::control::Command) -> Result<()> {
+    use anyhow::Context;
+
+    use crate::control;
+
+    let path = control::default_path().context("cannot locate the control socket")?;
+    if let control::Command::Watch = cmd {
+        return control::watch(&path);
+    }
+
+    let reply = control::request(&path, cmd)
+        .with_context(|| format!("failed to reach fluyendo at {}", path.display()))?;
+    if let Some(err) = reply.get("error").and_then(|err| err.as_str()) {
+        bail!("{err}");
+    }
+    if let control::Command::Status = cmd {
+        println!("{reply}");
+    }
+
+    Ok(())
+}
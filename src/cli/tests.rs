@@ -0,0 +1,18 @@
+use super::*;
+
+fn parse_args(args: &[&str]) -> Result<Command> {
+    parse(args.iter().map(|arg| arg.to_string()))
+}
+
+#[test]
+fn parses_list_devices() {
+    assert!(matches!(
+        parse_args(&["--list-devices"]),
+        Ok(Command::ListDevices)
+    ));
+}
+
+#[test]
+fn rejects_unknown_arguments() {
+    assert!(parse_args(&["--list-device"]).is_err());
+}
@@ -0,0 +1,66 @@
+use std::time::SystemTime;
+
+use crate::Instant;
+
+pub trait Clock {
+    fn now(&self) -> Instant;
+    fn wall(&self) -> SystemTime;
+}
+
+#[derive(Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn wall(&self) -> SystemTime {
+        SystemTime::now()
+    }
+
+    // `std::time::SystemTime::now` panics on wasm32-unknown-unknown.
+    #[cfg(target_arch = "wasm32")]
+    fn wall(&self) -> SystemTime {
+        let since_epoch = wasmtimer::std::SystemTime::now()
+            .duration_since(wasmtimer::std::UNIX_EPOCH)
+            .unwrap_or_default();
+        std::time::UNIX_EPOCH + since_epoch
+    }
+}
+
+/// Only moves when told to, clones share the same time.
+#[cfg(test)]
+#[derive(Clone)]
+pub struct FakeClock {
+    origin: Instant,
+    wall_origin: SystemTime,
+    offset: std::rc::Rc<std::cell::Cell<iced::time::Duration>>,
+}
+
+#[cfg(test)]
+impl FakeClock {
+    pub fn new() -> Self {
+        Self {
+            origin: Instant::now(),
+            wall_origin: SystemTime::UNIX_EPOCH + iced::time::Duration::from_secs(1_700_000_000),
+            offset: Default::default(),
+        }
+    }
+
+    pub fn advance(&self, by: iced::time::Duration) {
+        self.offset.set(self.offset.get() + by);
+    }
+}
+
+#[cfg(test)]
+impl Clock for FakeClock {
+    fn now(&self) -> Instant {
+        self.origin + self.offset.get()
+    }
+
+    fn wall(&self) -> SystemTime {
+        self.wall_origin + self.offset.get()
+    }
+}
@@ -18,15 +18,17 @@ pub struct StateColorConfig {
     pub circle_background: Color,
 }
 
-#[derive(serde::Deserialize)]
+#[derive(Clone, serde::Deserialize)]
 #[serde(default)]
 pub struct ColorConfig {
     work: StateColorConfig,
     r#break: StateColorConfig,
+    long_break: StateColorConfig,
     start: StateColorConfig,
 
     pause_work: StateColorConfig,
     pause_break: StateColorConfig,
+    pause_long_break: StateColorConfig,
 }
 
 #[derive(Clone, Copy, serde::Deserialize)]
@@ -39,17 +41,70 @@ pub struct Color {
 }
 
 impl ColorConfig {
-    pub fn with_state(&self, state: &StateKind) -> &StateColorConfig {
+    /// Names of the per state tables, as written in the config.
+    pub const STATES: [&'static str; 7] = [
+        "start",
+        "work",
+        "break",
+        "long_break",
+        "pause_work",
+        "pause_break",
+        "pause_long_break",
+    ];
+
+    pub fn by_name(&self, name: &str) -> Option<&StateColorConfig> {
+        Some(match name {
+            "start" => &self.start,
+            "work" => &self.work,
+            "break" => &self.r#break,
+            "long_break" => &self.long_break,
+            "pause_work" => &self.pause_work,
+            "pause_break" => &self.pause_break,
+            "pause_long_break" => &self.pause_long_break,
+            _ => return None,
+        })
+    }
+
+    pub fn with_state(&self, state: &StateKind, long_break: bool) -> &StateColorConfig {
         match state {
             StateKind::Begin => &self.start,
             StateKind::Pause(PauseKind::Work) => &self.pause_work,
+            StateKind::Pause(PauseKind::Break) if long_break => &self.pause_long_break,
             StateKind::Pause(PauseKind::Break) => &self.pause_break,
             StateKind::Work => &self.work,
+            StateKind::Break if long_break => &self.long_break,
             StateKind::Break => &self.r#break,
         }
     }
 }
 
+impl StateColorConfig {
+    pub const FIELDS: [&'static str; 8] = [
+        "title_text",
+        "timer_text",
+        "button_text",
+        "button_background",
+        "background",
+        "active_circle",
+        "pending_circle",
+        "circle_background",
+    ];
+
+    pub fn by_name(&self, name: &str) -> Option<Color> {
+        Some(match name {
+            "title_text" => self.title_text,
+            "timer_text" => self.timer_text,
+            "button_text" => self.button_text,
+            "button_background" => self.button_background,
+            "background" => self.background,
+            "active_circle" => self.active_circle,
+            "pending_circle" => self.pending_circle,
+            "circle_background" => self.circle_background,
+            _ => return None,
+        })
+    }
+}
+
 impl Color {
     pub const fn rgb(rgb: u32) -> Self {
         assert!(rgb < 0x1_00_00_00);
@@ -77,6 +132,13 @@ impl TryFrom<String> for Color {
     }
 }
 
+impl std::fmt::Display for Color {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let Color { r, g, b, a } = *self;
+        f.write_str(&csscolorparser::Color::new(r, g, b, a).to_css_hex())
+    }
+}
+
 impl From<Color> for iced::Color {
     fn from(value: Color) -> Self {
         let Color { r, g, b, a } = value;
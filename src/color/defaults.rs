@@ -39,6 +39,14 @@ impl Default for ColorConfig {
             ..default_per_state
         };
 
+        let long_break = StateColorConfig {
+            title_text: Color::rgb(0xffd27f),
+            circle_background: Color::rgb(0x8a6a00),
+            active_circle: Color::rgb(0xd9a93b),
+            pending_circle: Color::rgb(0xfae08c),
+            ..default_per_state
+        };
+
         let start = StateColorConfig {
             title_text: Color::rgb(0x90aecf),
             circle_background: Color::rgb(0x3c4b5b),
@@ -54,13 +62,19 @@ impl Default for ColorConfig {
             title_text: Color::rgb(0xc48400),
             ..start
         };
+        let pause_long_break = StateColorConfig {
+            title_text: Color::rgb(0xd9a93b),
+            ..start
+        };
 
         Self {
             work,
             r#break,
+            long_break,
             start,
             pause_work,
             pause_break,
+            pause_long_break,
         }
     }
 }
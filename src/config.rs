@@ -1,32 +1,153 @@
+use std::collections::BTreeMap;
 use std::path::Path;
 
-use anyhow::Result;
 use iced::time::Duration;
 use serde::Deserialize;
 
 use crate::color::ColorConfig;
+use crate::hooks::HooksConfig;
+use crate::keybindings::Keybindings;
+use crate::notification::NotificationConfig;
 
-#[derive(Deserialize)]
+#[cfg(test)]
+mod tests;
+mod validate;
+
+pub use validate::{ConfigError, ConfigErrors};
+
+#[derive(Clone, Deserialize)]
 #[serde(default)]
 pub struct Config {
     #[serde(with = "humantime_serde")]
     pub work_expected_duration: Duration,
     pub break_divisor: f32,
     pub auto_break: bool,
+    pub long_break: Option<LongBreakConfig>,
 
     pub audio_param: crate::audio::Param,
+    pub sounds: crate::audio::Sounds,
+    pub alarm: crate::audio::AlarmConfig,
+    pub ambient: crate::audio::AmbientConfig,
+    /// From 0 to 1.
+    pub volume: f32,
     pub mute: bool,
+    /// Shell command playing `$FLUYENDO_SOUND` at `$FLUYENDO_VOLUME` percent,
+    /// the audio backend of static builds.
+    #[cfg(target_env = "musl")]
+    pub player_command: Option<String>,
+    /// As printed by `--list-devices`, the system default if unset.
+    #[cfg(all(not(target_arch = "wasm32"), not(target_env = "musl")))]
+    pub output_device: Option<String>,
 
     pub color_config: ColorConfig,
+    pub keybindings: Keybindings,
+
+    pub hooks: HooksConfig,
+    pub notifications: NotificationConfig,
+
+    /// Profile active on startup.
+    pub profile: Option<String>,
+    pub profiles: BTreeMap<String, Profile>,
+}
+
+/// Overrides applied on top of the rest of the config while the profile is active.
+#[derive(Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct Profile {
+    #[serde(with = "humantime_serde")]
+    pub work_expected_duration: Option<Duration>,
+    pub break_divisor: Option<f32>,
+    pub auto_break: Option<bool>,
+    pub long_break: Option<LongBreakConfig>,
+    /// Replaces `color_config` as a whole, colors it leaves out are the built-in ones.
+    pub color_config: Option<ColorConfig>,
+}
+
+#[derive(Clone, Copy, PartialEq, Deserialize)]
+pub struct LongBreakConfig {
+    /// Number of completed work intervals that grant a long break.
+    pub after: u32,
+    #[serde(flatten)]
+    pub bonus: LongBreakBonus,
+}
+
+#[derive(Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LongBreakBonus {
+    /// Added on top of the break earned by the last work interval.
+    Duration(#[serde(with = "humantime_serde")] Duration),
+    /// Applied to the break earned by the last work interval.
+    Multiplier(f32),
 }
 
 impl Config {
-    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self> {
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self, ConfigErrors> {
         let path = path.as_ref();
-        let content = std::fs::read_to_string(path)?;
-        let config = toml::from_str(&content)?;
+        let content = std::fs::read_to_string(path)
+            .map_err(|err| ConfigErrors(vec![ConfigError::new(err.to_string())]))?;
+
+        Self::parse(&content).map_err(ConfigErrors)
+    }
+
+    /// Fetches the content of the config at `url`, `None` if there is none.
+    #[cfg(target_arch = "wasm32")]
+    pub async fn fetch(url: String) -> Result<Option<String>, String> {
+        use wasm_bindgen::JsCast;
+        use wasm_bindgen_futures::JsFuture;
+
+        let error = |err: wasm_bindgen::JsValue| format!("failed to fetch {url}: {err:?}");
+        let window = web_sys::window().ok_or("no window to fetch the config in")?;
+        let response: web_sys::Response = JsFuture::from(window.fetch_with_str(&url))
+            .await
+            .and_then(|response| response.dyn_into())
+            .map_err(error)?;
+        if response.status() == 404 {
+            return Ok(None);
+        }
+        if !response.ok() {
+            return Err(format!("failed to fetch {url}: {}", response.status_text()));
+        }
+        let content = JsFuture::from(response.text().map_err(error)?)
+            .await
+            .map_err(error)?;
+        Ok(content.as_string())
+    }
+
+    /// The config as it is while the profile `name` is active.
+    pub fn with_profile(&self, name: Option<&str>) -> Self {
+        let mut config = self.clone();
+        let Some(profile) = name.and_then(|name| self.profiles.get(name)) else {
+            return config;
+        };
+        let Profile {
+            work_expected_duration,
+            break_divisor,
+            auto_break,
+            long_break,
+            color_config,
+        } = profile.clone();
+
+        config.work_expected_duration =
+            work_expected_duration.unwrap_or(config.work_expected_duration);
+        config.break_divisor = break_divisor.unwrap_or(config.break_divisor);
+        config.auto_break = auto_break.unwrap_or(config.auto_break);
+        config.long_break = long_break.or(config.long_break);
+        config.color_config = color_config.unwrap_or(config.color_config);
+        config
+    }
+
+    /// The volume sounds are played at, silent while muted.
+    pub fn output_volume(&self) -> f32 {
+        if self.mute {
+            0.0
+        } else {
+            self.volume
+        }
+    }
 
-        Ok(config)
+    /// Deserializes and validates the config, reporting every invalid value at once.
+    pub fn parse(content: &str) -> Result<Self, Vec<ConfigError>> {
+        validate::parse(content)
     }
 }
 
@@ -36,9 +157,23 @@ impl Default for Config {
             work_expected_duration: Duration::from_secs(25 * 60),
             break_divisor: 5.0,
             auto_break: true,
+            long_break: None,
             audio_param: Default::default(),
+            sounds: Default::default(),
+            alarm: Default::default(),
+            ambient: Default::default(),
+            volume: 1.0,
             mute: false,
+            #[cfg(target_env = "musl")]
+            player_command: None,
+            #[cfg(all(not(target_arch = "wasm32"), not(target_env = "musl")))]
+            output_device: None,
             color_config: ColorConfig::default(),
+            keybindings: Keybindings::default(),
+            hooks: HooksConfig::default(),
+            notifications: NotificationConfig::default(),
+            profile: None,
+            profiles: BTreeMap::new(),
         }
     }
 }
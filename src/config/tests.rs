@@ -0,0 +1,80 @@
+use super::validate::Location;
+use super::*;
+
+fn long_break(content: &str) -> Option<LongBreakConfig> {
+    match Config::parse(content) {
+        Ok(config) => config.long_break,
+        Err(errors) => panic!("{errors:?}"),
+    }
+}
+
+#[test]
+fn long_break_with_multiplier() {
+    let rule = long_break("[long_break]\nafter = 4\nmultiplier = 2\n");
+    assert!(
+        rule == Some(LongBreakConfig {
+            after: 4,
+            bonus: LongBreakBonus::Multiplier(2.0),
+        })
+    );
+}
+
+#[test]
+fn long_break_with_duration() {
+    let rule = long_break("[long_break]\nafter = 4\nduration = \"15m\"\n");
+    assert!(
+        rule == Some(LongBreakConfig {
+            after: 4,
+            bonus: LongBreakBonus::Duration(Duration::from_secs(15 * 60)),
+        })
+    );
+}
+
+#[test]
+fn long_break_multiplier_is_bounded() {
+    let Err(errors) = Config::parse("[long_break]\nafter = 4\nmultiplier = 1e30\n") else {
+        panic!("a huge multiplier was accepted");
+    };
+    assert_eq!(errors[0].key, "long_break.multiplier");
+}
+
+fn errors(content: &str) -> Vec<ConfigError> {
+    match Config::parse(content) {
+        Ok(_) => panic!("`{content}` was accepted"),
+        Err(errors) => errors,
+    }
+}
+
+#[test]
+fn tiny_break_divisor_is_rejected() {
+    let errors = errors("break_divisor = 1e-30\n");
+    assert_eq!(errors[0].key, "break_divisor");
+}
+
+#[test]
+fn type_error_in_a_nested_table_points_at_its_key() {
+    let errors = errors("volume = 0.5\n\n[ambient]\nvolume = \"loud\"\n");
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0].key, "ambient.volume");
+    assert_eq!(
+        errors[0].location,
+        Some(Location {
+            line: 4,
+            column: 10
+        })
+    );
+}
+
+#[test]
+fn invalid_profile_value_points_at_the_profile() {
+    let errors = errors("break_divisor = 5\n\n[profiles.deep]\nbreak_divisor = 0\n");
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0].key, "profiles.deep.break_divisor");
+    assert_eq!(
+        errors[0].location,
+        Some(Location {
+            line: 4,
+            column: 17
+        })
+    );
+}
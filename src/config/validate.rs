@@ -0,0 +1,245 @@
+use std::fmt;
+use std::ops::Range;
+
+use toml_edit::{ImDocument, Item, TableLike};
+
+use super::{Config, LongBreakBonus};
+
+/// Keeps long breaks within what a `Duration` can hold.
+const MAX_MULTIPLIER: f32 = 100.0;
+/// Same for breaks, which last up to ten times the work that earned them.
+const MIN_BREAK_DIVISOR: f32 = 0.1;
+
+/// A problem with the config file, pointing at the offending key when there is one.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConfigError {
+    /// Dotted path to the key, such as `long_break.after`, empty if no key is involved.
+    pub key: String,
+    pub location: Option<Location>,
+    pub message: String,
+}
+
+/// One-based position within the config file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Location {
+    pub line: usize,
+    pub column: usize,
+}
+
+#[derive(Debug)]
+pub struct ConfigErrors(pub Vec<ConfigError>);
+
+impl ConfigError {
+    pub fn new(message: impl Into<String>) -> Self {
+        Self {
+            key: String::new(),
+            location: None,
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(Location { line, column }) = self.location {
+            write!(f, "line {line}, column {column}: ")?;
+        }
+        if !self.key.is_empty() {
+            write!(f, "{}: ", self.key)?;
+        }
+        f.write_str(&self.message)
+    }
+}
+
+impl fmt::Display for ConfigErrors {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, err) in self.0.iter().enumerate() {
+            if i > 0 {
+                f.write_str("\n")?;
+            }
+            write!(f, "{err}")?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for ConfigErrors {}
+
+pub(super) fn parse(content: &str) -> Result<Config, Vec<ConfigError>> {
+    let doc = ImDocument::parse(content).map_err(|err| {
+        vec![ConfigError {
+            key: String::new(),
+            location: err.span().map(|span| locate(content, span.start)),
+            message: err.message().trim_end().to_string(),
+        }]
+    })?;
+    let source = Source { content, doc };
+
+    let config: Config = toml::from_str(content)
+        .map_err(|err| vec![source.error_at(err.span(), err.message().trim_end().to_string())])?;
+
+    let errors = source.validate(&config);
+    if errors.is_empty() {
+        Ok(config)
+    } else {
+        Err(errors)
+    }
+}
+
+struct Source<'a> {
+    content: &'a str,
+    doc: ImDocument<&'a str>,
+}
+
+impl Source<'_> {
+    fn validate(&self, config: &Config) -> Vec<ConfigError> {
+        let mut errors = self.check(config, &[]);
+        for name in config.profiles.keys() {
+            let profile = config.with_profile(Some(name));
+            errors.extend(self.check(&profile, &["profiles", name.as_str()]));
+        }
+        if let Some(name) = &config.profile {
+            if !config.profiles.contains_key(name) {
+                errors.push(self.error_for(&["profile"], format!("no profile named `{name}`")));
+            }
+        }
+
+        errors.sort_by_key(|err| err.location);
+        errors
+    }
+
+    /// Checks the values of `config`, with a `prefix` only those set under it are reported.
+    fn check(&self, config: &Config, prefix: &[&str]) -> Vec<ConfigError> {
+        let mut errors = vec![];
+        let mut check = |ok: bool, path: &[&str], message: &str| {
+            let path = [prefix, path].concat();
+            if !ok && (prefix.is_empty() || self.lookup(&path).is_some()) {
+                errors.push(self.error_for(&path, message.to_string()));
+            }
+        };
+
+        check(
+            !config.work_expected_duration.is_zero(),
+            &["work_expected_duration"],
+            "must be longer than zero",
+        );
+        check(
+            config.break_divisor.is_finite() && config.break_divisor >= MIN_BREAK_DIVISOR,
+            &["break_divisor"],
+            "must be a number of at least 0.1",
+        );
+        if let Some(rule) = config.long_break {
+            check(
+                rule.after > 0,
+                &["long_break", "after"],
+                "must be at least 1",
+            );
+            if let LongBreakBonus::Multiplier(multiplier) = rule.bonus {
+                check(
+                    (0.0..=MAX_MULTIPLIER).contains(&multiplier),
+                    &["long_break", "multiplier"],
+                    "must be between 0 and 100",
+                );
+            }
+        }
+        if prefix.is_empty() {
+            check(
+                (0.0..=1.0).contains(&config.volume),
+                &["volume"],
+                "must be between 0 and 1",
+            );
+            check(
+                (0.0..=1.0).contains(&config.ambient.volume),
+                &["ambient", "volume"],
+                "must be between 0 and 1",
+            );
+            if let Err(message) = crate::audio::check_param(&config.audio_param) {
+                errors.push(self.error_for(&["audio_param"], message));
+            }
+            for sound in crate::audio::Sound::ALL {
+                let source = config.sounds.get(sound);
+                if let Some(Err(message)) = source.map(crate::audio::check_source) {
+                    errors.push(self.error_for(&["sounds", sound.key()], message));
+                }
+            }
+            let ambient = config.ambient.sound.as_ref();
+            if let Some(Err(message)) = ambient.map(crate::audio::check_source) {
+                errors.push(self.error_for(&["ambient", "sound"], message));
+            }
+            for (action, other, combo) in config.keybindings.conflicts() {
+                // At least one of them is configured, defaults don't conflict with each other.
+                let (at, with) = match self.lookup(&["keybindings", other.name()]) {
+                    Some(_) => (other, action),
+                    None => (action, other),
+                };
+                errors.push(self.error_for(
+                    &["keybindings", at.name()],
+                    format!("`{combo}` is also bound to `{}`", with.name()),
+                ));
+            }
+        }
+
+        errors
+    }
+
+    fn lookup(&self, path: &[&str]) -> Option<&Item> {
+        let (first, rest) = path.split_first()?;
+        let mut item = self.doc.as_table().get(first)?;
+        for key in rest {
+            item = item.as_table_like()?.get(key)?;
+        }
+        Some(item)
+    }
+
+    fn error_for(&self, path: &[&str], message: String) -> ConfigError {
+        let span = self.lookup(path).and_then(Item::span);
+
+        ConfigError {
+            key: path.join("."),
+            location: span.map(|span| locate(self.content, span.start)),
+            message,
+        }
+    }
+
+    fn error_at(&self, span: Option<Range<usize>>, message: String) -> ConfigError {
+        let Some(span) = span else {
+            return ConfigError::new(message);
+        };
+        let mut path = vec![];
+        key_at(self.doc.as_table(), span.start, &mut path);
+
+        ConfigError {
+            key: path.join("."),
+            location: Some(locate(self.content, span.start)),
+            message,
+        }
+    }
+}
+
+/// Finds the innermost key whose name or value covers `offset`.
+fn key_at(table: &dyn TableLike, offset: usize, path: &mut Vec<String>) -> bool {
+    let covers = |span: Option<Range<usize>>| span.is_some_and(|span| span.contains(&offset));
+    for (name, item) in table.iter() {
+        path.push(name.to_string());
+        if let Some(inner) = item.as_table_like() {
+            if key_at(inner, offset, path) {
+                return true;
+            }
+        }
+        let key_span = table.get_key_value(name).and_then(|(key, _)| key.span());
+        if covers(key_span) || covers(item.span()) {
+            return true;
+        }
+        path.pop();
+    }
+    false
+}
+
+fn locate(content: &str, offset: usize) -> Location {
+    let before = content.get(..offset).unwrap_or(content);
+    let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+    Location {
+        line: before.matches('\n').count() + 1,
+        column: before[line_start..].chars().count() + 1,
+    }
+}
@@ -0,0 +1,277 @@
+use std::hash::{Hash, Hasher};
+use std::io::{BufRead, BufReader, Write};
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use anyhow::{bail, Result};
+use iced::futures::channel::mpsc;
+use iced::Subscription;
+use serde::Serialize;
+
+use crate::state::{State, StateKind};
+use crate::Event;
+
+const SOCKET_FILE: &str = "control.sock";
+
+const WATCH_INTERVAL: Duration = Duration::from_secs(1);
+const RECONNECT_INTERVAL: Duration = Duration::from_secs(5);
+
+#[derive(Clone, Copy)]
+pub enum Command {
+    Start,
+    Stop,
+    Pause,
+    Reload,
+    CancelAudio,
+    Status,
+    Watch,
+}
+
+#[derive(Clone, Serialize)]
+pub struct Status {
+    pub state: StateKind,
+    pub name: String,
+    pub time: String,
+    pub elapsed_secs: f64,
+    pub limit_secs: f64,
+    pub completed_ratio: f32,
+    pub class: &'static str,
+}
+
+#[derive(Clone)]
+pub struct Server {
+    shared: Arc<Shared>,
+    _socket: Arc<SocketFile>,
+}
+
+/// Removes the socket once the last handle to the server is gone.
+struct SocketFile(PathBuf);
+
+struct Shared {
+    status: Mutex<Status>,
+    // Filled in once the app subscribes to the server.
+    events: Mutex<Option<mpsc::UnboundedSender<Event>>>,
+}
+
+pub fn default_path() -> Option<PathBuf> {
+    crate::dirs::runtime_dir().map(|dir| dir.join(SOCKET_FILE))
+}
+
+impl Command {
+    pub fn parse(s: &str) -> Option<Self> {
+        Some(match s {
+            "start" => Self::Start,
+            "stop" => Self::Stop,
+            "pause" => Self::Pause,
+            "reload" => Self::Reload,
+            "cancel-audio" => Self::CancelAudio,
+            "status" => Self::Status,
+            "watch" => Self::Watch,
+            _ => return None,
+        })
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Start => "start",
+            Self::Stop => "stop",
+            Self::Pause => "pause",
+            Self::Reload => "reload",
+            Self::CancelAudio => "cancel-audio",
+            Self::Status => "status",
+            Self::Watch => "watch",
+        }
+    }
+
+    fn event(self) -> Option<Event> {
+        Some(match self {
+            Self::Start => Event::Start,
+            Self::Stop => Event::Stop,
+            Self::Pause => Event::Pause,
+            Self::Reload => Event::Reload,
+            Self::CancelAudio => Event::CancelAudio,
+            Self::Status | Self::Watch => return None,
+        })
+    }
+}
+
+impl Status {
+    fn of(state: &State) -> Self {
+        let (elapsed, limit) = state.elapsed_and_limit();
+        Self {
+            state: *state.kind(),
+            name: state.name(),
+            time: state.time(),
+            elapsed_secs: elapsed.as_secs_f64(),
+            limit_secs: limit.as_secs_f64(),
+            completed_ratio: state.completed_ratio(),
+            class: state.slug(),
+        }
+    }
+
+    // See https://github.com/Alexays/Waybar/wiki/Module:-Custom
+    fn waybar(&self) -> serde_json::Value {
+        serde_json::json!({
+            "text": self.time.trim(),
+            "alt": self.class,
+            "tooltip": self.name,
+            "class": self.class,
+            "percentage": (self.completed_ratio * 100.0).round() as u8,
+        })
+    }
+}
+
+impl Server {
+    pub fn bind(path: &Path, state: &State) -> Result<Self> {
+        let listener = bind_listener(path)?;
+        let this = Self {
+            shared: Arc::new(Shared {
+                status: Mutex::new(Status::of(state)),
+                events: Mutex::new(None),
+            }),
+            _socket: Arc::new(SocketFile(path.to_path_buf())),
+        };
+
+        let shared = this.shared.clone();
+        std::thread::spawn(move || {
+            for stream in listener.incoming() {
+                let stream = match stream {
+                    Ok(stream) => stream,
+                    Err(err) => {
+                        eprintln!("control socket error: {err}");
+                        continue;
+                    }
+                };
+                let shared = shared.clone();
+                std::thread::spawn(move || {
+                    if let Err(err) = shared.serve(stream) {
+                        eprintln!("control connection error: {err}");
+                    }
+                });
+            }
+        });
+
+        Ok(this)
+    }
+
+    pub fn publish(&self, state: &State) {
+        *self.shared.status.lock().unwrap() = Status::of(state);
+    }
+
+    pub fn events(&self) -> mpsc::UnboundedReceiver<Event> {
+        let (sender, receiver) = mpsc::unbounded();
+        *self.shared.events.lock().unwrap() = Some(sender);
+        receiver
+    }
+
+    pub fn subscription(&self) -> Subscription<Event> {
+        Subscription::run_with(self.clone(), Self::events)
+    }
+}
+
+impl Hash for Server {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        Arc::as_ptr(&self.shared).hash(state);
+    }
+}
+
+impl Drop for SocketFile {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.0);
+    }
+}
+
+impl Shared {
+    fn serve(&self, stream: UnixStream) -> Result<()> {
+        let mut writer = stream.try_clone()?;
+        for line in BufReader::new(stream).lines() {
+            let line = line?;
+            let cmd = line.trim();
+            let reply = match Command::parse(cmd) {
+                None => serde_json::json!({ "error": format!("unknown command: {cmd}") }),
+                Some(Command::Status) => serde_json::to_value(&*self.status.lock().unwrap())?,
+                Some(Command::Watch) => return self.watch(&mut writer),
+                Some(cmd) => self.dispatch(cmd),
+            };
+            writeln!(writer, "{reply}")?;
+        }
+
+        Ok(())
+    }
+
+    fn watch(&self, writer: &mut UnixStream) -> Result<()> {
+        loop {
+            let status = self.status.lock().unwrap().clone();
+            writeln!(writer, "{}", status.waybar())?;
+            std::thread::sleep(WATCH_INTERVAL);
+        }
+    }
+
+    fn dispatch(&self, cmd: Command) -> serde_json::Value {
+        let Some(event) = cmd.event() else {
+            return serde_json::json!({ "ok": true });
+        };
+        let events = self.events.lock().unwrap();
+        match events.as_ref().map(|events| events.unbounded_send(event)) {
+            Some(Ok(())) => serde_json::json!({ "ok": true }),
+            Some(Err(_)) | None => {
+                serde_json::json!({ "error": "app is not accepting commands" })
+            }
+        }
+    }
+}
+
+pub fn request(path: &Path, cmd: Command) -> Result<serde_json::Value> {
+    let mut stream = UnixStream::connect(path)?;
+    writeln!(stream, "{}", cmd.as_str())?;
+
+    let mut reply = String::new();
+    BufReader::new(stream).read_line(&mut reply)?;
+
+    Ok(serde_json::from_str(&reply)?)
+}
+
+/// Relays status lines of a running instance to stdout until killed, waiting
+/// for the instance to come back if it's not running.
+pub fn watch(path: &Path) -> Result<()> {
+    let offline = serde_json::json!({ "text": "", "alt": "offline", "class": "offline" });
+    let mut stdout = std::io::stdout().lock();
+
+    loop {
+        if let Ok(mut stream) = UnixStream::connect(path) {
+            writeln!(stream, "{}", Command::Watch.as_str())?;
+            for line in BufReader::new(stream).lines() {
+                let Ok(line) = line else {
+                    break;
+                };
+                writeln!(stdout, "{line}")?;
+                stdout.flush()?;
+            }
+        }
+
+        writeln!(stdout, "{offline}")?;
+        stdout.flush()?;
+        std::thread::sleep(RECONNECT_INTERVAL);
+    }
+}
+
+fn bind_listener(path: &Path) -> Result<UnixListener> {
+    if let Some(dir) = path.parent() {
+        crate::dirs::create_private_dir(dir)?;
+    }
+
+    match UnixListener::bind(path) {
+        Ok(listener) => return Ok(listener),
+        Err(err) if err.kind() != std::io::ErrorKind::AddrInUse => return Err(err.into()),
+        Err(_) => {}
+    }
+    if UnixStream::connect(path).is_ok() {
+        bail!("another instance is listening at {}", path.display());
+    }
+    // Left behind by an instance that didn't shut down cleanly.
+    std::fs::remove_file(path)?;
+
+    Ok(UnixListener::bind(path)?)
+}
@@ -0,0 +1,82 @@
+use std::io::{BufRead, Write};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use anyhow::Result;
+use iced::time::Duration;
+use serde::{Deserialize, Serialize};
+
+const HISTORY_FILE: &str = "history.jsonl";
+
+#[derive(Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum IntervalKind {
+    Work,
+    Break,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Record {
+    pub kind: IntervalKind,
+    #[serde(with = "humantime_serde")]
+    pub start: SystemTime,
+    #[serde(with = "humantime_serde")]
+    pub end: SystemTime,
+    #[serde(with = "humantime_serde")]
+    pub elapsed: Duration,
+    #[serde(with = "humantime_serde")]
+    pub break_earned: Duration,
+    pub paused: bool,
+}
+
+pub fn default_path() -> Option<PathBuf> {
+    crate::dirs::data_dir().map(|dir| dir.join(HISTORY_FILE))
+}
+
+pub fn append(path: &Path, record: &Record) -> Result<()> {
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+
+    let mut line = serde_json::to_vec(record)?;
+    line.push(b'\n');
+
+    // A single write per record keeps lines intact even with several writers.
+    std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)?
+        .write_all(&line)?;
+
+    Ok(())
+}
+
+pub fn read(path: &Path) -> Result<Vec<Record>> {
+    let file = match std::fs::File::open(path) {
+        Ok(file) => file,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(vec![]),
+        Err(err) => return Err(err.into()),
+    };
+
+    // A damaged line only costs its own record, the rest of the history still counts.
+    let mut records = vec![];
+    for (n, line) in std::io::BufReader::new(file).lines().enumerate() {
+        let line = match line {
+            Ok(line) => line,
+            Err(err) if err.kind() == std::io::ErrorKind::InvalidData => {
+                eprintln!("skipping line {} of {}: {}", n + 1, path.display(), err);
+                continue;
+            }
+            Err(err) => return Err(err.into()),
+        };
+        if line.trim().is_empty() {
+            continue;
+        }
+        match serde_json::from_str(&line) {
+            Ok(record) => records.push(record),
+            Err(err) => eprintln!("skipping line {} of {}: {}", n + 1, path.display(), err),
+        }
+    }
+
+    Ok(records)
+}
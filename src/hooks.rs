@@ -0,0 +1,92 @@
+use serde::Deserialize;
+
+use crate::state::State;
+
+#[derive(Default, Clone, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct HooksConfig {
+    pub work_started: Option<String>,
+    pub work_completed: Option<String>,
+    pub break_started: Option<String>,
+    pub break_exhausted: Option<String>,
+    pub paused: Option<String>,
+    pub resumed: Option<String>,
+}
+
+#[derive(Clone, Copy)]
+pub enum Transition {
+    WorkStarted,
+    WorkCompleted,
+    BreakStarted,
+    BreakExhausted,
+    Paused,
+    Resumed,
+}
+
+impl Transition {
+    fn name(self) -> &'static str {
+        match self {
+            Self::WorkStarted => "work_started",
+            Self::WorkCompleted => "work_completed",
+            Self::BreakStarted => "break_started",
+            Self::BreakExhausted => "break_exhausted",
+            Self::Paused => "paused",
+            Self::Resumed => "resumed",
+        }
+    }
+}
+
+impl HooksConfig {
+    fn command(&self, transition: Transition) -> Option<&str> {
+        match transition {
+            Transition::WorkStarted => &self.work_started,
+            Transition::WorkCompleted => &self.work_completed,
+            Transition::BreakStarted => &self.break_started,
+            Transition::BreakExhausted => &self.break_exhausted,
+            Transition::Paused => &self.paused,
+            Transition::Resumed => &self.resumed,
+        }
+        .as_deref()
+    }
+
+    pub fn run(&self, transition: Transition, state: &State) {
+        let Some(cmd) = self.command(transition) else {
+            return;
+        };
+
+        let (elapsed, limit) = state.elapsed_and_limit();
+        let env = [
+            ("FLUYENDO_TRANSITION", transition.name().to_string()),
+            ("FLUYENDO_STATE", state.slug().to_string()),
+            ("FLUYENDO_ELAPSED", elapsed.as_secs().to_string()),
+            ("FLUYENDO_LIMIT", limit.as_secs().to_string()),
+            (
+                "FLUYENDO_SAVED_BREAK",
+                state.saved_break_time.as_secs().to_string(),
+            ),
+        ];
+        spawn(cmd, &env);
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn spawn(cmd: &str, env: &[(&str, String)]) {
+    #[cfg(windows)]
+    let mut command = std::process::Command::new("cmd");
+    #[cfg(windows)]
+    command.arg("/C");
+    #[cfg(not(windows))]
+    let mut command = std::process::Command::new("sh");
+    #[cfg(not(windows))]
+    command.arg("-c");
+
+    command.arg(cmd).envs(env.iter().map(|(k, v)| (k, v)));
+    match command.spawn() {
+        // Reap the child in the background so it doesn't stay a zombie.
+        Ok(mut child) => drop(std::thread::spawn(move || child.wait())),
+        Err(err) => eprintln!("failed to run hook `{cmd}`: {err}"),
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+fn spawn(_: &str, _: &[(&str, String)]) {}
@@ -0,0 +1,388 @@
+use std::collections::BTreeMap;
+use std::fmt;
+
+use serde::Deserialize;
+
+use crate::Event;
+
+#[cfg(test)]
+mod tests;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Action {
+    Start,
+    Stop,
+    Pause,
+    Reload,
+    CancelAudio,
+    ToggleMute,
+    VolumeUp,
+    VolumeDown,
+    NextProfile,
+    ShowStats,
+    ShowSettings,
+    ShowTimer,
+    Quit,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KeyCombo {
+    pub ctrl: bool,
+    pub alt: bool,
+    pub shift: bool,
+    pub logo: bool,
+    pub key: Key,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Key {
+    /// Lowercase, space included.
+    Char(char),
+    Escape,
+    Enter,
+    Tab,
+    Backspace,
+    Delete,
+    Up,
+    Down,
+    Left,
+    Right,
+    Home,
+    End,
+    PageUp,
+    PageDown,
+    F(u8),
+}
+
+/// Keys bound to each action, actions missing from the config keep their default keys.
+#[derive(Clone, PartialEq, Deserialize)]
+#[serde(from = "BTreeMap<Action, Keys>")]
+pub struct Keybindings(BTreeMap<Action, Vec<KeyCombo>>);
+
+/// Either a single key or a list of them.
+struct Keys(Vec<KeyCombo>);
+
+const NAMED_KEYS: &[(&str, Key)] = &[
+    ("esc", Key::Escape),
+    ("enter", Key::Enter),
+    ("tab", Key::Tab),
+    ("backspace", Key::Backspace),
+    ("delete", Key::Delete),
+    ("up", Key::Up),
+    ("down", Key::Down),
+    ("left", Key::Left),
+    ("right", Key::Right),
+    ("home", Key::Home),
+    ("end", Key::End),
+    ("pageup", Key::PageUp),
+    ("pagedown", Key::PageDown),
+    ("space", Key::Char(' ')),
+    ("plus", Key::Char('+')),
+];
+
+impl Action {
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Start => "start",
+            Self::Stop => "stop",
+            Self::Pause => "pause",
+            Self::Reload => "reload",
+            Self::CancelAudio => "cancel_audio",
+            Self::ToggleMute => "toggle_mute",
+            Self::VolumeUp => "volume_up",
+            Self::VolumeDown => "volume_down",
+            Self::NextProfile => "next_profile",
+            Self::ShowStats => "show_stats",
+            Self::ShowSettings => "show_settings",
+            Self::ShowTimer => "show_timer",
+            Self::Quit => "quit",
+        }
+    }
+
+    pub fn event(self) -> Event {
+        match self {
+            Self::Start => Event::Start,
+            Self::Stop => Event::Stop,
+            Self::Pause => Event::Pause,
+            Self::Reload => Event::Reload,
+            Self::CancelAudio => Event::CancelAudio,
+            Self::ToggleMute => Event::ToggleMute,
+            Self::VolumeUp => Event::ChangeVolume(crate::VOLUME_STEP),
+            Self::VolumeDown => Event::ChangeVolume(-crate::VOLUME_STEP),
+            Self::NextProfile => Event::NextProfile,
+            Self::ShowStats => Event::ShowStats,
+            Self::ShowSettings => Event::ShowSettings,
+            Self::ShowTimer => Event::ShowTimer,
+            Self::Quit => Event::Quit,
+        }
+    }
+
+    fn default_keys(self) -> &'static str {
+        match self {
+            Self::Start => "s",
+            Self::Stop => "x",
+            Self::Pause => "p",
+            Self::Reload => "z",
+            Self::CancelAudio => "esc",
+            Self::ToggleMute => "m",
+            Self::VolumeUp => "up",
+            Self::VolumeDown => "down",
+            Self::NextProfile => "n",
+            Self::ShowStats => "t",
+            Self::ShowSettings => ",",
+            Self::ShowTimer => "backspace",
+            Self::Quit => "q",
+        }
+    }
+}
+
+impl Keybindings {
+    pub fn action_for(&self, pressed: &KeyCombo) -> Option<Action> {
+        self.0
+            .iter()
+            .find(|(_, combos)| combos.iter().any(|combo| combo.matches(pressed)))
+            .map(|(action, _)| *action)
+    }
+
+    /// The key shown next to the action, if it has any.
+    pub fn key_of(&self, action: Action) -> Option<&KeyCombo> {
+        self.0.get(&action)?.first()
+    }
+
+    /// Pairs of actions that a key press could trigger both of, only the first would fire.
+    pub fn conflicts(&self) -> Vec<(Action, Action, KeyCombo)> {
+        let overlap = |a: &KeyCombo, b: &KeyCombo| a.matches(b) || b.matches(a);
+        let mut conflicts = vec![];
+        for (i, (action, combos)) in self.0.iter().enumerate() {
+            for (other, other_combos) in self.0.iter().skip(i + 1) {
+                let conflict = combos
+                    .iter()
+                    .find(|combo| other_combos.iter().any(|other| overlap(combo, other)));
+                if let Some(combo) = conflict {
+                    conflicts.push((*action, *other, *combo));
+                }
+            }
+        }
+        conflicts
+    }
+}
+
+impl Default for Keybindings {
+    fn default() -> Self {
+        Self::from(BTreeMap::new())
+    }
+}
+
+impl From<BTreeMap<Action, Keys>> for Keybindings {
+    fn from(mut configured: BTreeMap<Action, Keys>) -> Self {
+        let all = [
+            Action::Start,
+            Action::Stop,
+            Action::Pause,
+            Action::Reload,
+            Action::CancelAudio,
+            Action::ToggleMute,
+            Action::VolumeUp,
+            Action::VolumeDown,
+            Action::NextProfile,
+            Action::ShowStats,
+            Action::ShowSettings,
+            Action::ShowTimer,
+            Action::Quit,
+        ];
+        let bindings = all
+            .into_iter()
+            .map(|action| {
+                let combos = match configured.remove(&action) {
+                    Some(Keys(combos)) => combos,
+                    None => vec![action
+                        .default_keys()
+                        .parse()
+                        .expect("default keys are valid")],
+                };
+                (action, combos)
+            })
+            .collect();
+
+        Self(bindings)
+    }
+}
+
+impl KeyCombo {
+    pub fn new(key: Key) -> Self {
+        Self {
+            ctrl: false,
+            alt: false,
+            shift: false,
+            logo: false,
+            key,
+        }
+    }
+
+    /// Shift is only compared if the binding asks for it or the key isn't a character,
+    /// so `z` also works with caps lock, and `?` without spelling out `shift+/`.
+    fn matches(&self, pressed: &KeyCombo) -> bool {
+        self.key == pressed.key
+            && self.ctrl == pressed.ctrl
+            && self.alt == pressed.alt
+            && self.logo == pressed.logo
+            && (self.shift == pressed.shift || (!self.shift && matches!(self.key, Key::Char(_))))
+    }
+
+    pub fn from_iced(
+        key: &iced::keyboard::Key,
+        modifiers: iced::keyboard::Modifiers,
+    ) -> Option<Self> {
+        use iced::keyboard::key::Named;
+
+        let key = match key {
+            iced::keyboard::Key::Character(c) => {
+                let mut chars = c.chars().flat_map(char::to_lowercase);
+                match (chars.next(), chars.next()) {
+                    (Some(c), None) => Key::Char(c),
+                    _ => return None,
+                }
+            }
+            iced::keyboard::Key::Named(named) => match named {
+                Named::Space => Key::Char(' '),
+                Named::Escape => Key::Escape,
+                Named::Enter => Key::Enter,
+                Named::Tab => Key::Tab,
+                Named::Backspace => Key::Backspace,
+                Named::Delete => Key::Delete,
+                Named::ArrowUp => Key::Up,
+                Named::ArrowDown => Key::Down,
+                Named::ArrowLeft => Key::Left,
+                Named::ArrowRight => Key::Right,
+                Named::Home => Key::Home,
+                Named::End => Key::End,
+                Named::PageUp => Key::PageUp,
+                Named::PageDown => Key::PageDown,
+                Named::F1 => Key::F(1),
+                Named::F2 => Key::F(2),
+                Named::F3 => Key::F(3),
+                Named::F4 => Key::F(4),
+                Named::F5 => Key::F(5),
+                Named::F6 => Key::F(6),
+                Named::F7 => Key::F(7),
+                Named::F8 => Key::F(8),
+                Named::F9 => Key::F(9),
+                Named::F10 => Key::F(10),
+                Named::F11 => Key::F(11),
+                Named::F12 => Key::F(12),
+                _ => return None,
+            },
+            iced::keyboard::Key::Unidentified => return None,
+        };
+
+        Some(Self {
+            ctrl: modifiers.control(),
+            alt: modifiers.alt(),
+            shift: modifiers.shift(),
+            logo: modifiers.logo(),
+            key,
+        })
+    }
+}
+
+impl std::str::FromStr for KeyCombo {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts: Vec<_> = s.split('+').map(str::trim).collect();
+        let key = parts.pop().filter(|key| !key.is_empty());
+        let Some(key) = key else {
+            return Err(format!("missing key in `{s}`, use `plus` for the + key"));
+        };
+
+        let lowercase = key.to_lowercase();
+        let mut chars = lowercase.chars();
+        let key = match (chars.next(), chars.next()) {
+            (Some(c), None) => Key::Char(c),
+            _ => NAMED_KEYS
+                .iter()
+                .find(|(name, _)| *name == lowercase)
+                .map(|(_, key)| *key)
+                .or_else(|| {
+                    let n = lowercase.strip_prefix('f')?.parse().ok()?;
+                    (1..=12).contains(&n).then_some(Key::F(n))
+                })
+                .ok_or_else(|| format!("unknown key `{key}`"))?,
+        };
+
+        let mut combo = Self::new(key);
+        for modifier in parts {
+            match modifier.to_lowercase().as_str() {
+                "ctrl" | "control" => combo.ctrl = true,
+                "alt" | "option" => combo.alt = true,
+                "shift" => combo.shift = true,
+                "super" | "logo" | "cmd" | "meta" => combo.logo = true,
+                _ => return Err(format!("unknown modifier `{modifier}`")),
+            }
+        }
+
+        Ok(combo)
+    }
+}
+
+impl<'de> Deserialize<'de> for KeyCombo {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        s.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+impl<'de> Deserialize<'de> for Keys {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct Visitor;
+
+        impl<'de> serde::de::Visitor<'de> for Visitor {
+            type Value = Keys;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("a key or a list of keys")
+            }
+
+            fn visit_str<E: serde::de::Error>(self, s: &str) -> Result<Keys, E> {
+                s.parse().map(|combo| Keys(vec![combo])).map_err(E::custom)
+            }
+
+            fn visit_seq<A: serde::de::SeqAccess<'de>>(self, mut seq: A) -> Result<Keys, A::Error> {
+                let mut combos = vec![];
+                while let Some(combo) = seq.next_element()? {
+                    combos.push(combo);
+                }
+                Ok(Keys(combos))
+            }
+        }
+
+        deserializer.deserialize_any(Visitor)
+    }
+}
+
+impl fmt::Display for KeyCombo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (on, name) in [
+            (self.ctrl, "ctrl+"),
+            (self.alt, "alt+"),
+            (self.shift, "shift+"),
+            (self.logo, "super+"),
+        ] {
+            if on {
+                f.write_str(name)?;
+            }
+        }
+
+        match self.key {
+            Key::F(n) => write!(f, "f{n}"),
+            key => match NAMED_KEYS.iter().find(|(_, named)| *named == key) {
+                Some((name, _)) => f.write_str(name),
+                None => match key {
+                    Key::Char(c) => write!(f, "{c}"),
+                    _ => unreachable!("every other key is named"),
+                },
+            },
+        }
+    }
+}
@@ -0,0 +1,78 @@
+use super::*;
+
+fn combo(s: &str) -> KeyCombo {
+    s.parse().unwrap_or_else(|err| panic!("{err}"))
+}
+
+fn bindings(content: &str) -> Keybindings {
+    toml::from_str(content).unwrap()
+}
+
+#[test]
+fn parses_keys_and_modifiers() {
+    assert_eq!(combo("Z"), KeyCombo::new(Key::Char('z')));
+    assert_eq!(combo("space"), KeyCombo::new(Key::Char(' ')));
+    assert_eq!(combo("plus"), KeyCombo::new(Key::Char('+')));
+    assert_eq!(
+        combo("Ctrl + shift+F5"),
+        KeyCombo {
+            ctrl: true,
+            shift: true,
+            ..KeyCombo::new(Key::F(5))
+        }
+    );
+    assert_eq!(
+        combo("cmd+option+pageup"),
+        KeyCombo {
+            alt: true,
+            logo: true,
+            ..KeyCombo::new(Key::PageUp)
+        }
+    );
+}
+
+#[test]
+fn rejects_malformed_keys() {
+    for s in ["", "ctrl+", "+", "f13", "enterr", "hyper+a"] {
+        assert!(s.parse::<KeyCombo>().is_err(), "`{s}` was accepted");
+    }
+}
+
+#[test]
+fn displays_as_parsed() {
+    for s in ["z", "ctrl+alt+pageup", "shift+f12", "space", "plus"] {
+        assert_eq!(combo(s).to_string(), s);
+    }
+}
+
+#[test]
+fn defaults_do_not_conflict() {
+    assert!(Keybindings::default().conflicts().is_empty());
+}
+
+#[test]
+fn same_key_conflicts() {
+    let conflicts = bindings("start = \"p\"").conflicts();
+    assert_eq!(
+        conflicts,
+        [(Action::Start, Action::Pause, KeyCombo::new(Key::Char('p')))]
+    );
+}
+
+#[test]
+fn key_without_shift_conflicts_with_its_shifted_binding() {
+    let conflicts = bindings("cancel_audio = \"shift+z\"").conflicts();
+    assert_eq!(conflicts.len(), 1);
+    assert_eq!(
+        (conflicts[0].0, conflicts[0].1),
+        (Action::Reload, Action::CancelAudio)
+    );
+}
+
+#[test]
+fn other_modifiers_do_not_conflict() {
+    assert!(bindings("cancel_audio = \"ctrl+z\"").conflicts().is_empty());
+    assert!(bindings("cancel_audio = \"shift+up\"")
+        .conflicts()
+        .is_empty());
+}
@@ -1,9 +1,10 @@
 use anyhow::Result;
 
-use config::Config;
+use config::{Config, ConfigError, ConfigErrors};
 use iced::Element;
 use iced::Length;
 use iced::Subscription;
+use iced::Task;
 
 #[cfg(not(target_arch = "wasm32"))]
 use iced::time::Instant;
@@ -11,24 +12,75 @@ use iced::time::Instant;
 use wasmtimer::std::Instant;
 
 mod audio;
+#[cfg(not(target_arch = "wasm32"))]
+mod cli;
+mod clock;
 mod color;
 mod config;
+#[cfg(unix)]
+mod control;
+mod dirs;
+mod history;
+mod hooks;
+mod keybindings;
+mod notification;
+mod persist;
 mod ring;
+mod settings;
 mod state;
+mod stats;
+#[cfg(not(target_arch = "wasm32"))]
+mod tui;
+#[cfg(not(target_arch = "wasm32"))]
+mod watch;
 
-use color::StateColorConfig;
+use audio::Sound;
+use clock::Clock;
+use color::{ColorConfig, StateColorConfig};
+use hooks::Transition;
+use keybindings::{Action, KeyCombo};
 use ring::RingSemiPending;
-use state::{PauseKind, State, StateKind};
+use settings::Settings;
+use state::{duration_to_str, PauseKind, State, StateKind};
+use stats::{Stats, Summary};
 
-const DEFAULT_CONFIG_PATH: &str = "./config.toml";
+/// Only used when there is no home directory to look up the config in.
+const FALLBACK_CONFIG_PATH: &str = "./config.toml";
+#[cfg(not(target_arch = "wasm32"))]
+const CONFIG_PATH_VAR: &str = "FLUYENDO_CONFIG";
+const ERROR_TEXT_COLOR: iced::Color = iced::Color::from_rgb(0.9, 0.3, 0.3);
+const RELOAD_NOTICE_DURATION: iced::time::Duration = iced::time::Duration::from_secs(3);
+/// How much the volume keys change the volume by.
+const VOLUME_STEP: f32 = 0.1;
 
 struct App {
     pub state: State,
+    pub screen: Screen,
+    /// In effect, that is `base_config` with the active profile applied.
     pub config: Config,
+    pub base_config: Config,
+    pub profile: Option<String>,
     pub config_path: String,
+    /// Problems found by the last reload, the previous config stays in effect meanwhile.
+    pub config_errors: Vec<ConfigError>,
+    /// When the config was last reloaded and whether that succeeded.
+    pub last_reload: Option<(Instant, bool)>,
 
     pub audio_started_once: bool,
     pub audio: audio::Controller,
+    pub ambient_playing: bool,
+
+    // Aborted on drop, so a late click on an outdated notification does nothing.
+    pub pending_notification: Option<iced::task::Handle>,
+
+    #[cfg(unix)]
+    pub control: Option<control::Server>,
+}
+
+pub enum Screen {
+    Timer,
+    Stats(Stats),
+    Settings(Box<Settings>),
 }
 
 pub enum ButtonKind {
@@ -45,101 +97,428 @@ pub enum Event {
     Stop,
     Pause,
     CancelAudio,
+    ShowStats,
+    ShowSettings,
+    ShowTimer,
+    Quit,
+    Settings(settings::Message),
+    SelectProfile(Option<String>),
+    NextProfile,
+    ToggleMute,
+    /// Changes the volume by the given amount.
+    ChangeVolume(f32),
+    SetVolume(f32),
+    KeyPressed(KeyCombo),
+    #[cfg(target_arch = "wasm32")]
+    ChooseAlarm,
+    /// `None` going back to the configured alarm.
+    #[cfg(target_arch = "wasm32")]
+    AlarmChosen(Option<String>),
+    /// The content of the config, fetched since there is no file to read on the web.
+    #[cfg(target_arch = "wasm32")]
+    ConfigFetched(Result<Option<String>, String>),
+    /// Only redraws, the warning is read from the audio controller.
+    #[cfg(all(not(target_arch = "wasm32"), not(target_env = "musl")))]
+    AudioWarningChanged,
 }
 
 impl App {
     fn color_config(&self) -> &StateColorConfig {
-        self.config.color_config.with_state(self.state.kind())
+        self.config
+            .color_config
+            .with_state(self.state.kind(), self.state.long_break)
     }
 
     fn subscription(&self) -> Subscription<Event> {
-        let ticks = if self.state.kind.needs_tick() {
+        let ticks = if self.state.kind.needs_tick() || self.reload_notice().is_some() {
             iced::time::every(iced::time::Duration::from_millis(300)).map(Event::TimerTick)
         } else {
             Subscription::none()
         };
 
-        let reloader = iced::event::listen_with(|event, _status, _window| match event {
-            iced::Event::Keyboard(iced::keyboard::Event::KeyPressed {
-                key: iced::keyboard::Key::Character(c),
-                ..
-            }) if c == "z" || c == "Z" => Some(Event::Reload),
-            iced::Event::Keyboard(iced::keyboard::Event::KeyPressed {
-                key: iced::keyboard::Key::Named(iced::keyboard::key::Named::Escape),
-                ..
-            }) => Some(Event::CancelAudio),
+        // Only a function pointer is accepted here, the bindings are looked up in `update`.
+        let keys = iced::event::listen_with(|event, status, _window| match event {
+            iced::Event::Keyboard(iced::keyboard::Event::KeyPressed { key, modifiers, .. })
+                if status == iced::event::Status::Ignored =>
+            {
+                KeyCombo::from_iced(&key, modifiers).map(Event::KeyPressed)
+            }
             _ => None,
         });
 
-        Subscription::batch(vec![ticks, reloader])
+        #[allow(unused_mut)]
+        let mut subscriptions = vec![ticks, keys];
+        #[cfg(not(target_arch = "wasm32"))]
+        subscriptions.push(watch::subscription(&self.config_path));
+        #[cfg(unix)]
+        if let Some(control) = &self.control {
+            subscriptions.push(control.subscription());
+        }
+        #[cfg(all(not(target_arch = "wasm32"), not(target_env = "musl")))]
+        subscriptions.push(
+            self.audio
+                .warning_changes()
+                .map(|()| Event::AudioWarningChanged),
+        );
+
+        Subscription::batch(subscriptions)
     }
 
     fn update_config(&mut self, new_config: Config) {
+        let is_profile_gone = self
+            .profile
+            .as_ref()
+            .is_some_and(|name| !new_config.profiles.contains_key(name));
+        if is_profile_gone {
+            self.profile = new_config.profile.clone();
+        }
+        self.base_config = new_config;
+        self.apply_config(self.base_config.with_profile(self.profile.as_deref()));
+    }
+
+    /// Leaving the settings any other way than saving drops the changes.
+    fn leave_settings(&mut self) {
+        if let Screen::Settings(settings) = &mut self.screen {
+            if let settings::Action::Close(Some(config)) =
+                settings.update(settings::Message::Discard)
+            {
+                self.update_config(config);
+            }
+            self.screen = Screen::Timer;
+        }
+    }
+
+    /// Lets open settings know about muting or volume changes made outside of them.
+    fn follow_runtime_changes(&mut self) {
+        if let Screen::Settings(settings) = &mut self.screen {
+            settings.follow_runtime_changes(&self.base_config);
+        }
+    }
+
+    fn reloaded(&mut self, res: Result<Config, ConfigErrors>) {
+        match res {
+            Ok(new_config) => {
+                self.config_errors.clear();
+                self.last_reload = Some((Instant::now(), true));
+                self.update_config(new_config);
+            }
+            Err(errors) => {
+                self.config_errors = errors.0;
+                self.last_reload = Some((Instant::now(), false));
+            }
+        }
+    }
+
+    fn switch_profile(&mut self, profile: Option<String>) {
+        self.profile = profile;
+        self.apply_config(self.base_config.with_profile(self.profile.as_deref()));
+    }
+
+    /// Profiles in the order the selector shows them, `None` being no profile.
+    fn profiles(&self) -> impl Iterator<Item = Option<&String>> {
+        std::iter::once(None).chain(self.base_config.profiles.keys().map(Some))
+    }
+
+    fn apply_config(&mut self, new_config: Config) {
         let Config {
             audio_param: audio_file_path,
+            sounds,
+            alarm,
+            ambient,
+            volume,
             mute,
+            #[cfg(target_env = "musl")]
+            player_command,
+            #[cfg(all(not(target_arch = "wasm32"), not(target_env = "musl")))]
+            output_device,
 
             color_config: _,
             work_expected_duration: _,
             break_divisor: _,
             auto_break: _,
+            long_break: _,
+            keybindings: _,
+            hooks: _,
+            notifications: _,
+            profile: _,
+            profiles: _,
         } = std::mem::replace(&mut self.config, new_config);
-        let is_audio_changed = audio_file_path != self.config.audio_param;
-        let is_mute_changed = mute != self.config.mute;
+        let is_audio_changed =
+            audio_file_path != self.config.audio_param || sounds != self.config.sounds;
+        let is_volume_changed = volume != self.config.volume || mute != self.config.mute;
 
         if is_audio_changed {
-            self.audio.update(self.config.audio_param.clone());
+            self.audio
+                .update(self.config.audio_param.clone(), self.config.sounds.clone());
         }
-        if is_mute_changed {
-            if self.config.mute {
-                self.audio.mute();
-            } else {
-                self.audio.unmute();
-            }
+        #[cfg(target_env = "musl")]
+        if player_command != self.config.player_command {
+            self.audio.set_player(self.config.player_command.clone());
+        }
+        #[cfg(all(not(target_arch = "wasm32"), not(target_env = "musl")))]
+        if output_device != self.config.output_device {
+            self.audio.set_device(self.config.output_device.clone());
+        }
+        if alarm != self.config.alarm {
+            self.audio.set_alarm(self.config.alarm);
+        }
+        if ambient != self.config.ambient {
+            self.audio.set_ambient(self.config.ambient.clone());
+        }
+        if is_volume_changed {
+            self.audio.set_volume(self.config.output_volume());
         }
 
         self.state.update_config(&self.config);
     }
 
-    fn update(&mut self, ev: Event) {
+    fn update(&mut self, ev: Event) -> Task<Event> {
+        let mut task = Task::none();
         match ev {
-            Event::Reload => match Config::from_file(&self.config_path) {
-                Ok(new_config) => self.update_config(new_config),
-                Err(err) => {
-                    eprintln!("failed to read config at {}: {}", self.config_path, err)
-                }
-            },
+            #[cfg(not(target_arch = "wasm32"))]
+            Event::Reload => self.reloaded(Config::from_file(&self.config_path)),
+            #[cfg(target_arch = "wasm32")]
+            Event::Reload => {
+                task = Task::perform(
+                    Config::fetch(self.config_path.clone()),
+                    Event::ConfigFetched,
+                );
+            }
+            #[cfg(target_arch = "wasm32")]
+            Event::ConfigFetched(Ok(Some(content))) => {
+                self.reloaded(Config::parse(&content).map_err(ConfigErrors));
+            }
+            // Without a config next to the page the defaults stay in effect.
+            #[cfg(target_arch = "wasm32")]
+            Event::ConfigFetched(Ok(None)) => {}
+            #[cfg(target_arch = "wasm32")]
+            Event::ConfigFetched(Err(err)) => {
+                self.reloaded(Err(ConfigErrors(vec![ConfigError::new(err)])));
+            }
             Event::TimerTick(at) => {
                 self.state.on_tick_at(at);
-                if !self.audio_started_once && self.state.is_completed() {
-                    self.audio.start();
+                // Ticks also keep the reload notice up while paused, which completes nothing.
+                let is_running = self.state.kind.needs_tick();
+                if is_running && !self.audio_started_once && self.state.is_completed() {
+                    self.audio.play(match self.state.kind {
+                        StateKind::Break if self.state.long_break => Sound::LongBreakComplete,
+                        StateKind::Break => Sound::BreakComplete,
+                        _ => Sound::WorkComplete,
+                    });
                     self.audio_started_once = true;
+                    self.run_hook(match self.state.kind {
+                        StateKind::Break => Transition::BreakExhausted,
+                        _ => Transition::WorkCompleted,
+                    });
+                    task = self.notify();
                 }
             }
             Event::Start => {
+                notification::prepare();
+                self.pending_notification = None;
                 self.audio.stop();
                 let was_paused = self.state.kind.is_paused();
+                let was_running = self.state.kind.needs_tick();
+                let is_resuming = self.state.interval_started_at.is_some();
                 self.state.start();
                 if !was_paused {
                     self.audio_started_once = false;
                 }
+                if !was_running {
+                    self.audio.play(Sound::Click);
+                    self.run_hook(match self.state.kind {
+                        _ if is_resuming => Transition::Resumed,
+                        StateKind::Break => Transition::BreakStarted,
+                        _ => Transition::WorkStarted,
+                    });
+                }
             }
             Event::Stop => {
+                self.pending_notification = None;
                 self.audio.stop();
                 let was_paused = self.state.kind.is_paused();
                 self.state.stop();
                 if !was_paused {
                     self.audio_started_once = false;
                 }
+                if let StateKind::Break = self.state.kind {
+                    self.run_hook(Transition::BreakStarted);
+                }
             }
             Event::Pause => {
+                self.pending_notification = None;
                 self.audio.stop();
+                let was_running = self.state.kind.needs_tick();
                 self.state.pause();
+                if was_running {
+                    self.audio.play(Sound::Click);
+                    self.run_hook(Transition::Paused);
+                }
             }
             Event::CancelAudio => {
                 self.audio.stop();
             }
+            Event::ShowStats => {
+                self.leave_settings();
+                let records = self
+                    .state
+                    .history_path
+                    .as_deref()
+                    .map_or_else(Vec::new, |path| {
+                        history::read(path).unwrap_or_else(|err| {
+                            eprintln!("failed to read history at {}: {}", path.display(), err);
+                            vec![]
+                        })
+                    });
+                self.screen = Screen::Stats(Stats::from_records(&records, self.state.clock.wall()));
+            }
+            // There is no file to edit on the web.
+            #[cfg(target_arch = "wasm32")]
+            Event::ShowSettings => {}
+            #[cfg(not(target_arch = "wasm32"))]
+            Event::ShowSettings => {
+                // Opening them again would take the edits so far for the original config.
+                if !matches!(self.screen, Screen::Settings(_)) {
+                    self.screen = Screen::Settings(Box::new(Settings::open(
+                        &self.config_path,
+                        &self.base_config,
+                    )));
+                }
+            }
+            Event::ShowTimer => {
+                self.leave_settings();
+                self.screen = Screen::Timer;
+            }
+            Event::Quit => {
+                task = iced::exit();
+            }
+            Event::KeyPressed(combo) => {
+                let action = self.config.keybindings.action_for(&combo);
+                // Buttons that aren't shown don't react to their keys either.
+                let is_available = |action: Action| {
+                    ButtonKind::all()
+                        .find(|button| button.action() == action)
+                        .is_none_or(|button| button.text(self.state.kind()).is_some())
+                };
+                if let Some(action) = action.filter(|action| is_available(*action)) {
+                    return self.update(action.event());
+                }
+            }
+            Event::ToggleMute => {
+                let mut config = self.base_config.clone();
+                config.mute = !config.mute;
+                self.update_config(config);
+                self.follow_runtime_changes();
+            }
+            Event::ChangeVolume(delta) => {
+                return self.update(Event::SetVolume(self.base_config.volume + delta));
+            }
+            Event::SetVolume(volume) => {
+                let mut config = self.base_config.clone();
+                // Rounded so that stepping up and down lands on the same values.
+                config.volume = ((volume * 100.0).round() / 100.0).clamp(0.0, 1.0);
+                self.update_config(config);
+                self.follow_runtime_changes();
+            }
+            #[cfg(target_arch = "wasm32")]
+            Event::ChooseAlarm => {
+                if let Some(chosen) = self.audio.choose_file() {
+                    task = Task::future(async move { chosen.await.ok() })
+                        .and_then(|url| Task::done(Event::AlarmChosen(Some(url))));
+                }
+            }
+            #[cfg(target_arch = "wasm32")]
+            Event::AlarmChosen(url) => self.audio.choose_alarm(url),
+            #[cfg(all(not(target_arch = "wasm32"), not(target_env = "musl")))]
+            Event::AudioWarningChanged => {}
+            Event::SelectProfile(profile) => self.switch_profile(profile),
+            Event::NextProfile => {
+                let next = self
+                    .profiles()
+                    .skip_while(|profile| *profile != self.profile.as_ref())
+                    .nth(1)
+                    .flatten()
+                    .cloned();
+                self.switch_profile(next);
+            }
+            Event::Settings(msg) => {
+                if let Screen::Settings(settings) = &mut self.screen {
+                    match settings.update(msg) {
+                        settings::Action::None => {}
+                        settings::Action::Apply(config) => self.update_config(config),
+                        settings::Action::Close(config) => {
+                            if let Some(config) = config {
+                                self.update_config(config);
+                            }
+                            self.screen = Screen::Timer;
+                        }
+                    }
+                }
+            }
+        }
+
+        self.sync_ambient();
+        #[cfg(unix)]
+        if let Some(control) = &self.control {
+            control.publish(&self.state);
+        }
+
+        task
+    }
+
+    /// Plays the ambient loop while working, and during breaks if so configured.
+    fn sync_ambient(&mut self) {
+        let ambient = &self.config.ambient;
+        let is_wanted = ambient.sound.is_some()
+            && match self.state.kind {
+                StateKind::Work => true,
+                StateKind::Break => ambient.during_break,
+                StateKind::Begin | StateKind::Pause(_) => false,
+            };
+        if is_wanted != self.ambient_playing {
+            self.ambient_playing = is_wanted;
+            self.audio.play_ambient(is_wanted);
+        }
+    }
+
+    fn notify(&mut self) -> Task<Event> {
+        let config = &self.config.notifications;
+        if !config.enabled {
+            return Task::none();
         }
+
+        let (title, body) = match self.state.kind {
+            StateKind::Break => (&config.break_title, &config.break_body),
+            _ => (&config.work_title, &config.work_body),
+        };
+        let action = ButtonKind::Stop.text(self.state.kind()).unwrap_or("Stop");
+        let Some(acted) = notification::show(
+            notification::render(title, &self.state),
+            notification::render(body, &self.state),
+            action,
+        ) else {
+            return Task::none();
+        };
+
+        let (task, handle) = Task::future(async move { acted.await.ok() })
+            .and_then(|()| Task::done(Event::Stop))
+            .abortable();
+        self.pending_notification = Some(handle.abort_on_drop());
+        task
+    }
+
+    /// Briefly tells whether the last reload, manual or automatic, took effect.
+    fn reload_notice(&self) -> Option<&'static str> {
+        let (at, succeeded) = self.last_reload?;
+        (at.elapsed() < RELOAD_NOTICE_DURATION).then_some(if succeeded {
+            "Config reloaded"
+        } else {
+            "Config reload failed"
+        })
+    }
+
+    fn run_hook(&self, transition: Transition) {
+        self.config.hooks.run(transition, &self.state);
     }
 
     fn make_ring(&self) -> RingSemiPending {
@@ -176,6 +555,23 @@ impl App {
     fn view(&self) -> Element<Event> {
         use iced::widget;
 
+        let content = match &self.screen {
+            Screen::Timer => self.view_timer(),
+            Screen::Stats(stats) => self.view_stats(stats),
+            Screen::Settings(settings) => self.view_settings(settings),
+        };
+
+        widget::container(content)
+            .style(|_| {
+                widget::container::background(iced::Color::from(self.color_config().background))
+            })
+            .center_x(Length::Fixed(400.))
+            .into()
+    }
+
+    fn view_timer(&self) -> iced::widget::Column<'_, Event> {
+        use iced::widget;
+
         let ring = self.make_ring();
 
         let mut controls = widget::Row::with_capacity(ButtonKind::all().size_hint().0 * 2);
@@ -197,8 +593,42 @@ impl App {
 
             controls = controls.push(widget::Space::new().width(Length::FillPortion(3)));
         }
-        let columns = widget::column![
+        let profiles = (!self.base_config.profiles.is_empty()).then(|| {
+            let choices: Vec<_> = self
+                .profiles()
+                .map(|profile| ProfileChoice(profile.cloned()))
+                .collect();
+            widget::pick_list(
+                choices,
+                Some(ProfileChoice(self.profile.clone())),
+                |ProfileChoice(profile)| Event::SelectProfile(profile),
+            )
+        });
+        // Picking a file is easier than hosting it next to the page and configuring it.
+        #[cfg(target_arch = "wasm32")]
+        let alarm: Option<Element<Event>> = Some(
+            widget::row![
+                widget::button("Choose alarm")
+                    .on_press(Event::ChooseAlarm)
+                    .style(self.button_style()),
+                self.audio.has_chosen_alarm().then(|| {
+                    widget::button("Default alarm")
+                        .on_press(Event::AlarmChosen(None))
+                        .style(self.button_style())
+                }),
+            ]
+            .spacing(12)
+            .into(),
+        );
+        #[cfg(not(target_arch = "wasm32"))]
+        let alarm: Option<Element<Event>> = None;
+        #[cfg(not(target_env = "musl"))]
+        let audio_warning = self.audio.warning();
+        #[cfg(target_env = "musl")]
+        let audio_warning: Option<String> = None;
+        widget::column![
             widget::Space::new().height(Length::FillPortion(1)),
+            profiles,
             widget::text(self.state.name())
                 .color(self.color_config().title_text)
                 .height(Length::FillPortion(2))
@@ -210,16 +640,184 @@ impl App {
                 .color(self.color_config().timer_text)
                 .size(16)
                 .height(Length::FillPortion(1)),
-            widget::Space::new().height(Length::FillPortion(2)),
+            widget::text(self.reload_notice().unwrap_or_default())
+                .color(if self.config_errors.is_empty() {
+                    self.color_config().timer_text.into()
+                } else {
+                    ERROR_TEXT_COLOR
+                })
+                .size(12),
+            widget::Column::with_children(self.config_errors.iter().map(|err| {
+                widget::text(format!("{}: {err}", self.config_path))
+                    .color(ERROR_TEXT_COLOR)
+                    .size(12)
+                    .into()
+            })),
+            audio_warning.map(|warning| widget::text(warning).color(ERROR_TEXT_COLOR).size(12)),
+            widget::row![
+                widget::text(if self.config.mute { "Muted" } else { "Volume" })
+                    .color(self.color_config().timer_text)
+                    .size(12),
+                widget::slider(0.0..=1.0, self.config.volume, Event::SetVolume)
+                    .step(0.05)
+                    .width(Length::Fixed(160.)),
+            ]
+            .spacing(8)
+            .align_y(iced::Alignment::Center),
+            widget::row![
+                widget::button("Statistics")
+                    .on_press(Event::ShowStats)
+                    .style(self.button_style()),
+                // There is no file to edit on the web.
+                (!cfg!(target_arch = "wasm32")).then(|| {
+                    widget::button("Settings")
+                        .on_press(Event::ShowSettings)
+                        .style(self.button_style())
+                }),
+            ]
+            .spacing(12),
+            alarm,
+            widget::Space::new().height(Length::FillPortion(1)),
         ]
-        .align_x(iced::Alignment::Center);
+        .align_x(iced::Alignment::Center)
+    }
 
-        widget::container(columns)
-            .style(|_| {
-                widget::container::background(iced::Color::from(self.color_config().background))
-            })
-            .center_x(Length::Fixed(400.))
+    fn view_settings<'a>(&'a self, settings: &'a Settings) -> iced::widget::Column<'a, Event> {
+        use iced::widget;
+        use settings::{Field, Message};
+
+        let colors = self.color_config();
+        let field = |field: Field| {
+            let control: Element<Event> = if field.is_flag() {
+                widget::checkbox(settings.flag(field))
+                    .on_toggle(move |on| Event::Settings(Message::Toggle(field, on)))
+                    .into()
+            } else {
+                widget::text_input("default", settings.input(field))
+                    .on_input(move |text| Event::Settings(Message::Edit(field, text)))
+                    .width(Length::Fixed(160.))
+                    .into()
+            };
+            widget::column![
+                widget::row![
+                    widget::text(field.label())
+                        .color(colors.timer_text)
+                        .width(Length::Fill),
+                    control,
+                ]
+                .align_y(iced::Alignment::Center),
+                settings
+                    .error(field)
+                    .map(|err| widget::text(err).color(ERROR_TEXT_COLOR).size(12)),
+            ]
+            .width(Length::Fixed(340.))
             .into()
+        };
+
+        let form = widget::column![
+            widget::Column::with_children(Field::GENERAL.iter().map(|&f| field(f))).spacing(6),
+            widget::row![
+                widget::text("Colors")
+                    .color(colors.title_text)
+                    .size(20)
+                    .width(Length::Fill),
+                widget::pick_list(ColorConfig::STATES, Some(settings.colors()), |state| {
+                    Event::Settings(Message::SelectColors(state))
+                }),
+            ]
+            .width(Length::Fixed(340.))
+            .align_y(iced::Alignment::Center),
+            widget::Column::with_children(
+                StateColorConfig::FIELDS
+                    .iter()
+                    .map(|&name| field(Field::Color(settings.colors(), name)))
+            )
+            .spacing(6),
+            widget::Column::with_children(
+                settings
+                    .other_errors()
+                    .map(|err| { widget::text(err).color(ERROR_TEXT_COLOR).size(12).into() })
+            ),
+        ]
+        .spacing(12)
+        .align_x(iced::Alignment::Center);
+
+        widget::column![
+            widget::Space::new().height(Length::FillPortion(1)),
+            widget::text("Settings").color(colors.title_text).size(24),
+            widget::Space::new().height(Length::FillPortion(1)),
+            widget::scrollable(form).height(Length::FillPortion(12)),
+            widget::Space::new().height(Length::FillPortion(1)),
+            widget::row![
+                widget::button("Save")
+                    .on_press_maybe(
+                        settings
+                            .can_save()
+                            .then_some(Event::Settings(Message::Save))
+                    )
+                    .style(self.button_style()),
+                widget::button("Discard")
+                    .on_press(Event::Settings(Message::Discard))
+                    .style(self.button_style()),
+            ]
+            .spacing(12),
+            widget::Space::new().height(Length::FillPortion(1)),
+        ]
+        .align_x(iced::Alignment::Center)
+    }
+
+    fn view_stats<'a>(&'a self, stats: &Stats) -> iced::widget::Column<'a, Event> {
+        use iced::widget;
+
+        let colors = self.color_config();
+        let section = |title: &'static str, summary: &Summary| {
+            let line = |label: &'static str, value: String| {
+                widget::row![
+                    widget::text(label)
+                        .color(colors.timer_text)
+                        .width(Length::Fill),
+                    widget::text(value).color(colors.timer_text),
+                ]
+                .width(Length::Fixed(300.))
+            };
+
+            widget::column![
+                widget::text(title).color(colors.title_text).size(20),
+                line("Focus time", duration_to_str(summary.focus)),
+                line("Sessions", summary.sessions.to_string()),
+                line(
+                    "Average session",
+                    duration_to_str(summary.average_session())
+                ),
+                line("Break earned", duration_to_str(summary.break_earned)),
+                line("Break spent", duration_to_str(summary.break_spent)),
+            ]
+            .spacing(4)
+        };
+
+        widget::column![
+            widget::Space::new().height(Length::FillPortion(1)),
+            widget::text("Statistics").color(colors.title_text).size(24),
+            widget::Space::new().height(Length::FillPortion(1)),
+            section("Today", &stats.today),
+            widget::Space::new().height(Length::FillPortion(1)),
+            section("This week", &stats.week),
+            widget::Space::new().height(Length::FillPortion(2)),
+            widget::button("Back")
+                .on_press(Event::ShowTimer)
+                .style(self.button_style()),
+            widget::Space::new().height(Length::FillPortion(1)),
+        ]
+        .align_x(iced::Alignment::Center)
+    }
+}
+
+#[derive(Clone, PartialEq)]
+struct ProfileChoice(Option<String>);
+
+impl std::fmt::Display for ProfileChoice {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.0.as_deref().unwrap_or("No profile"))
     }
 }
 
@@ -236,10 +834,14 @@ impl ButtonKind {
     }
 
     fn event(&self) -> Event {
+        self.action().event()
+    }
+
+    fn action(&self) -> Action {
         match self {
-            ButtonKind::Start => Event::Start,
-            ButtonKind::Stop => Event::Stop,
-            ButtonKind::Pause => Event::Pause,
+            ButtonKind::Start => Action::Start,
+            ButtonKind::Stop => Action::Stop,
+            ButtonKind::Pause => Action::Pause,
         }
     }
 
@@ -259,33 +861,58 @@ impl ButtonKind {
 }
 
 #[cfg(not(target_arch = "wasm32"))]
-fn init_config() -> Result<(Config, String)> {
-    let mut args = std::env::args().skip(1);
-    let (config, config_path) = match (args.next().as_deref(), args.next().as_deref()) {
-        (None, None) => (Config::default(), None),
-        (Some("-c"), Some(p)) | (Some("--config"), Some(p)) => {
-            use anyhow::Context;
-            (
-                Config::from_file(p).with_context(|| format!("at path {p}"))?,
-                Some(p.into()),
-            )
-        }
-        _ => {
-            println!("Usage: fluyendo [--config <path/to/config.toml>]");
-            std::process::exit(0);
-        }
-    };
-    let config_path = config_path.unwrap_or_else(|| DEFAULT_CONFIG_PATH.to_string());
+fn init_config(config_path: Option<String>) -> Result<(Config, String)> {
+    use anyhow::Context;
+
+    // An explicitly requested config has to exist, the default one is optional.
+    let explicit_path = config_path.or_else(|| {
+        std::env::var(CONFIG_PATH_VAR)
+            .ok()
+            .filter(|p| !p.is_empty())
+    });
+    let is_explicit = explicit_path.is_some();
+    let config_path = explicit_path.unwrap_or_else(default_config_path);
+
+    if !is_explicit && !std::path::Path::new(&config_path).exists() {
+        return Ok((Config::default(), config_path));
+    }
+    let config = Config::from_file(&config_path)
+        .with_context(|| format!("invalid config at {config_path}"))?;
 
     Ok((config, config_path))
 }
 
+#[cfg(not(target_arch = "wasm32"))]
+fn default_config_path() -> String {
+    dirs::config_dir()
+        .map(|dir| dir.join("config.toml").to_string_lossy().into_owned())
+        .unwrap_or_else(|| FALLBACK_CONFIG_PATH.to_string())
+}
+
 #[cfg(target_arch = "wasm32")]
-fn init_config() -> Result<(Config, String)> {
-    Ok((Config::default(), DEFAULT_CONFIG_PATH.to_string()))
+fn init_config(_: Option<String>) -> Result<(Config, String)> {
+    Ok((Config::default(), FALLBACK_CONFIG_PATH.to_string()))
 }
 
 fn main() -> Result<()> {
+    #[cfg(not(target_arch = "wasm32"))]
+    let (config_path, headless) = match cli::parse(std::env::args().skip(1))? {
+        cli::Command::Run {
+            config_path,
+            headless,
+        } => (config_path, headless),
+        #[cfg(unix)]
+        cli::Command::Ctl(cmd) => return cli::ctl(cmd),
+        #[cfg(all(not(target_arch = "wasm32"), not(target_env = "musl")))]
+        cli::Command::ListDevices => return cli::list_devices(),
+        cli::Command::Help => {
+            println!("{}", cli::USAGE);
+            return Ok(());
+        }
+    };
+    #[cfg(target_arch = "wasm32")]
+    let config_path = None;
+
     #[cfg(not(target_arch = "wasm32"))]
     // See https://github.com/iced-rs/iced/issues/1810
     if std::env::var("WAYLAND_DISPLAY").is_ok() {
@@ -293,25 +920,64 @@ fn main() -> Result<()> {
         unsafe { std::env::set_var("ICED_PRESENT_MODE", "mailbox") };
     }
 
-    let (config, config_path) = init_config()?;
-
-    let audio_path = config.audio_param.clone();
-    let audio = audio::Controller::new(audio_path);
-    if config.mute {
-        audio.mute();
-    } else {
-        audio.unmute();
-    }
-
-    let initial_state = std::cell::Cell::new(Some(App {
-        state: State::from_config(&config),
+    let (config, config_path) = init_config(config_path)?;
+
+    let audio = audio::Controller::new(config.audio_param.clone(), config.sounds.clone());
+    audio.set_volume(config.output_volume());
+    audio.set_alarm(config.alarm);
+    audio.set_ambient(config.ambient.clone());
+    #[cfg(target_env = "musl")]
+    audio.set_player(config.player_command.clone());
+    #[cfg(all(not(target_arch = "wasm32"), not(target_env = "musl")))]
+    audio.set_device(config.output_device.clone());
+
+    let base_config = config;
+    let profile = base_config.profile.clone();
+    let config = base_config.with_profile(profile.as_deref());
+
+    let state = State::from_config(&config);
+
+    #[cfg(unix)]
+    let control = control::default_path().and_then(|path| {
+        control::Server::bind(&path, &state)
+            .map_err(|err| eprintln!("control socket is disabled: {err}"))
+            .ok()
+    });
+
+    let app = App {
+        state,
+        screen: Screen::Timer,
         config,
+        base_config,
+        profile,
         config_path,
+        config_errors: vec![],
+        last_reload: None,
         audio_started_once: false,
         audio,
-    }));
+        ambient_playing: false,
+        pending_notification: None,
+        #[cfg(unix)]
+        control,
+    };
+
+    #[cfg(not(target_arch = "wasm32"))]
+    if headless {
+        return tui::run(app);
+    }
+
+    let initial_state = std::cell::Cell::new(Some(app));
     let app = iced::application(
-        move || initial_state.take().expect("boot called once"),
+        move || {
+            let app = initial_state.take().expect("boot called once");
+            // The config of the web build is fetched once it runs.
+            let task = if cfg!(target_arch = "wasm32") {
+                Task::done(Event::Reload)
+            } else {
+                Task::none()
+            };
+            (app, task)
+        },
         App::update,
         App::view,
     )
@@ -0,0 +1,52 @@
+use serde::Deserialize;
+
+use crate::state::{duration_to_str, State};
+
+#[cfg(all(unix, not(target_os = "macos")))]
+mod freedesktop;
+#[cfg(all(unix, not(target_os = "macos")))]
+pub use freedesktop::*;
+
+#[cfg(target_arch = "wasm32")]
+mod web;
+#[cfg(target_arch = "wasm32")]
+pub use web::*;
+
+#[cfg(not(any(all(unix, not(target_os = "macos")), target_arch = "wasm32")))]
+mod stub;
+#[cfg(not(any(all(unix, not(target_os = "macos")), target_arch = "wasm32")))]
+pub use stub::*;
+
+#[derive(Clone, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct NotificationConfig {
+    pub enabled: bool,
+    pub work_title: String,
+    pub work_body: String,
+    pub break_title: String,
+    pub break_body: String,
+}
+
+impl Default for NotificationConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            work_title: "Work is done".into(),
+            work_body: "Worked for {elapsed}, {saved_break} of break saved".into(),
+            break_title: "Break is over".into(),
+            break_body: "Rested for {elapsed}".into(),
+        }
+    }
+}
+
+/// Substitutes `{state}`, `{elapsed}`, `{limit}` and `{saved_break}` in the template.
+pub fn render(template: &str, state: &State) -> String {
+    let (elapsed, limit) = state.elapsed_and_limit();
+    let duration = |d| duration_to_str(d).trim_start().to_string();
+
+    template
+        .replace("{state}", &state.name())
+        .replace("{elapsed}", &duration(elapsed))
+        .replace("{limit}", &duration(limit))
+        .replace("{saved_break}", &duration(state.saved_break_time))
+}
@@ -0,0 +1,29 @@
+use iced::futures::channel::oneshot;
+
+const APP_NAME: &str = "fluyendo";
+const ACTION_ID: &str = "stop";
+
+pub fn prepare() {}
+
+/// Resolves the receiver once the action button is pressed.
+pub fn show(title: String, body: String, action: &str) -> Option<oneshot::Receiver<()>> {
+    let mut notification = notify_rust::Notification::new();
+    notification
+        .appname(APP_NAME)
+        .summary(&title)
+        .body(&body)
+        .action(ACTION_ID, action);
+
+    let (sender, receiver) = oneshot::channel();
+    // Both showing and waiting talk to the session bus, keep it away from the UI thread.
+    std::thread::spawn(move || match notification.show() {
+        Ok(handle) => handle.wait_for_action(|action| {
+            if action == ACTION_ID {
+                let _ = sender.send(());
+            }
+        }),
+        Err(err) => eprintln!("failed to show notification: {err}"),
+    });
+
+    Some(receiver)
+}
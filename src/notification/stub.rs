@@ -0,0 +1,7 @@
+use iced::futures::channel::oneshot;
+
+pub fn prepare() {}
+
+pub fn show(_: String, _: String, _: &str) -> Option<oneshot::Receiver<()>> {
+    None
+}
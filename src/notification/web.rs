@@ -0,0 +1,36 @@
+use std::cell::Cell;
+
+use iced::futures::channel::oneshot;
+use wasm_bindgen::closure::Closure;
+use wasm_bindgen::JsCast;
+use web_sys::{Notification, NotificationOptions, NotificationPermission};
+
+/// Browsers only allow asking for permission from a user gesture.
+pub fn prepare() {
+    if Notification::permission() == NotificationPermission::Default {
+        let _ = Notification::request_permission();
+    }
+}
+
+/// Non-persistent web notifications can't have buttons, so a click is the action.
+pub fn show(title: String, body: String, _: &str) -> Option<oneshot::Receiver<()>> {
+    if Notification::permission() != NotificationPermission::Granted {
+        return None;
+    }
+
+    let options = NotificationOptions::new();
+    options.set_body(&body);
+    let notification = Notification::new_with_options(&title, &options).ok()?;
+
+    let (sender, receiver) = oneshot::channel();
+    let sender = Cell::new(Some(sender));
+    let onclick = Closure::<dyn FnMut()>::new(move || {
+        if let Some(sender) = sender.take() {
+            let _ = sender.send(());
+        }
+    });
+    notification.set_onclick(Some(onclick.as_ref().unchecked_ref()));
+    onclick.forget();
+
+    Some(receiver)
+}
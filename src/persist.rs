@@ -0,0 +1,58 @@
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use anyhow::Result;
+use iced::time::Duration;
+use serde::{Deserialize, Serialize};
+
+use crate::state::StateKind;
+
+const SNAPSHOT_FILE: &str = "state.toml";
+
+#[derive(Serialize, Deserialize)]
+pub struct Snapshot {
+    pub kind: StateKind,
+    #[serde(with = "humantime_serde")]
+    pub elapsed: Duration,
+    #[serde(with = "humantime_serde")]
+    pub saved_break_time: Duration,
+    #[serde(default)]
+    pub completed_work_intervals: u32,
+    #[serde(default)]
+    pub long_break: bool,
+    #[serde(default, with = "humantime_serde")]
+    pub interval_started_at: Option<SystemTime>,
+    #[serde(default)]
+    pub interval_paused: bool,
+    #[serde(with = "humantime_serde")]
+    pub saved_at: SystemTime,
+}
+
+pub fn default_path() -> Option<PathBuf> {
+    crate::dirs::state_dir().map(|dir| dir.join(SNAPSHOT_FILE))
+}
+
+impl Snapshot {
+    pub fn read(path: &Path) -> Result<Option<Self>> {
+        let content = match std::fs::read_to_string(path) {
+            Ok(content) => content,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(err) => return Err(err.into()),
+        };
+
+        Ok(Some(toml::from_str(&content)?))
+    }
+
+    pub fn write(&self, path: &Path) -> Result<()> {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+
+        // Write-then-rename so a crash mid-write never leaves a truncated snapshot.
+        let tmp = path.with_extension("toml.tmp");
+        std::fs::write(&tmp, toml::to_string(self)?)?;
+        std::fs::rename(tmp, path)?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,345 @@
+use std::collections::BTreeMap;
+
+use toml_edit::{DocumentMut, InlineTable, Item, Table, Value};
+
+#[cfg(not(target_arch = "wasm32"))]
+use crate::audio::SoundSource;
+use crate::color::{ColorConfig, StateColorConfig};
+use crate::config::{Config, ConfigError};
+
+#[cfg(test)]
+mod tests;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Field {
+    WorkExpectedDuration,
+    BreakDivisor,
+    AutoBreak,
+    Mute,
+    #[cfg(not(target_arch = "wasm32"))]
+    AudioParam,
+    /// A color of a state table, both named as in the config.
+    Color(&'static str, &'static str),
+}
+
+#[derive(Debug, Clone)]
+pub enum Message {
+    Edit(Field, String),
+    Toggle(Field, bool),
+    SelectColors(&'static str),
+    Save,
+    Discard,
+}
+
+pub enum Action {
+    None,
+    Apply(Config),
+    /// Leave the editor, switching to the given config if there is one.
+    Close(Option<Config>),
+}
+
+/// Edits the config file as a document rather than re-serializing `Config`,
+/// so comments, ordering and untouched keys survive a save.
+pub struct Settings {
+    path: String,
+    /// In effect when the settings were opened, restored on discard.
+    original: Config,
+    doc: DocumentMut,
+    inputs: BTreeMap<Field, String>,
+    flags: BTreeMap<Field, bool>,
+    errors: Vec<ConfigError>,
+    colors: &'static str,
+    /// Set when the file cannot be edited or saved, which disables saving.
+    problem: Option<String>,
+}
+
+impl Field {
+    pub const GENERAL: &[Field] = &[
+        Field::WorkExpectedDuration,
+        Field::BreakDivisor,
+        Field::AutoBreak,
+        Field::Mute,
+        #[cfg(not(target_arch = "wasm32"))]
+        Field::AudioParam,
+    ];
+
+    pub fn path(self) -> Vec<&'static str> {
+        match self {
+            Field::WorkExpectedDuration => vec!["work_expected_duration"],
+            Field::BreakDivisor => vec!["break_divisor"],
+            Field::AutoBreak => vec!["auto_break"],
+            Field::Mute => vec!["mute"],
+            #[cfg(not(target_arch = "wasm32"))]
+            Field::AudioParam => vec!["audio_param"],
+            Field::Color(state, name) => vec!["color_config", state, name],
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Field::WorkExpectedDuration => "Work duration",
+            Field::BreakDivisor => "Break divisor",
+            Field::AutoBreak => "Start breaks automatically",
+            Field::Mute => "Mute",
+            #[cfg(not(target_arch = "wasm32"))]
+            Field::AudioParam => "Alarm sound",
+            Field::Color(_, name) => name,
+        }
+    }
+
+    pub fn is_flag(self) -> bool {
+        matches!(self, Field::AutoBreak | Field::Mute)
+    }
+}
+
+impl Settings {
+    pub fn open(path: &str, config: &Config) -> Self {
+        let (content, mut problem) = match std::fs::read_to_string(path) {
+            Ok(content) => (content, None),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => (String::new(), None),
+            Err(err) => (String::new(), Some(format!("cannot read {path}: {err}"))),
+        };
+        let doc = content.parse().unwrap_or_else(|err: toml_edit::TomlError| {
+            problem = Some(format!("cannot edit {path}: {}", err.message().trim_end()));
+            DocumentMut::new()
+        });
+
+        let mut inputs = BTreeMap::new();
+        inputs.insert(
+            Field::WorkExpectedDuration,
+            humantime_serde::re::humantime::format_duration(config.work_expected_duration)
+                .to_string(),
+        );
+        inputs.insert(Field::BreakDivisor, config.break_divisor.to_string());
+        #[cfg(not(target_arch = "wasm32"))]
+        inputs.insert(
+            Field::AudioParam,
+            config
+                .audio_param
+                .as_ref()
+                .and_then(|source| match source {
+                    SoundSource::File(path) => Some(path.display().to_string()),
+                    // Shown as written in the file, in the inline form that editing accepts back.
+                    SoundSource::Tone(_) => {
+                        let mut value = doc.get("audio_param")?.clone().into_value().ok()?;
+                        value.decor_mut().clear();
+                        Some(value.to_string())
+                    }
+                })
+                .unwrap_or_default(),
+        );
+        for state in ColorConfig::STATES {
+            for name in StateColorConfig::FIELDS {
+                let color = config
+                    .color_config
+                    .by_name(state)
+                    .and_then(|colors| colors.by_name(name));
+                if let Some(color) = color {
+                    inputs.insert(Field::Color(state, name), color.to_string());
+                }
+            }
+        }
+
+        let flags = BTreeMap::from([
+            (Field::AutoBreak, config.auto_break),
+            (Field::Mute, config.mute),
+        ]);
+
+        let mut this = Self {
+            path: path.to_string(),
+            original: config.clone(),
+            doc,
+            inputs,
+            flags,
+            errors: vec![],
+            colors: ColorConfig::STATES[0],
+            problem,
+        };
+        this.follow_runtime_changes(config);
+        this
+    }
+
+    /// Muting and the volume change without going through the file, the document takes them
+    /// over so that applying an edit doesn't undo them.
+    pub fn follow_runtime_changes(&mut self, config: &Config) {
+        let default = Config::default();
+        let mute = self.doc.get("mute").and_then(Item::as_bool);
+        if mute.unwrap_or(default.mute) != config.mute {
+            set(self.doc.as_item_mut(), &["mute"], Some(config.mute.into()));
+        }
+        self.flags.insert(Field::Mute, config.mute);
+
+        let volume = self.doc.get("volume").and_then(|item| {
+            item.as_float()
+                .or_else(|| item.as_integer().map(|volume| volume as f64))
+        });
+        // Rounded the same way as the volume steps, a plain cast would write 0.3499999940395355.
+        let rounded = f64::from((config.volume * 100.0).round()) / 100.0;
+        if volume.unwrap_or(default.volume.into()) != rounded {
+            set(self.doc.as_item_mut(), &["volume"], Some(rounded.into()));
+        }
+    }
+
+    pub fn update(&mut self, msg: Message) -> Action {
+        match msg {
+            Message::Edit(field, text) => {
+                let value = match field {
+                    Field::BreakDivisor => text.trim().parse::<f64>().ok().map(Value::from),
+                    _ if text.trim().is_empty() => None,
+                    // A tone in its inline form, anything else is taken as a path.
+                    #[cfg(not(target_arch = "wasm32"))]
+                    Field::AudioParam => Some(
+                        text.trim()
+                            .parse::<Value>()
+                            .ok()
+                            .filter(Value::is_inline_table)
+                            .unwrap_or_else(|| Value::from(text.trim())),
+                    ),
+                    _ => Some(Value::from(text.trim())),
+                };
+                // Keep the last number in the document while the input isn't one.
+                if value.is_some() || field != Field::BreakDivisor {
+                    set(self.doc.as_item_mut(), &field.path(), value);
+                }
+                self.inputs.insert(field, text);
+                self.apply()
+            }
+            Message::Toggle(field, on) => {
+                set(self.doc.as_item_mut(), &field.path(), Some(on.into()));
+                self.flags.insert(field, on);
+                self.apply()
+            }
+            Message::SelectColors(state) => {
+                self.colors = state;
+                Action::None
+            }
+            Message::Save => match self.save() {
+                Ok(()) => Action::Close(None),
+                Err(err) => {
+                    self.problem = Some(format!("cannot save {}: {err}", self.path));
+                    Action::None
+                }
+            },
+            Message::Discard => Action::Close(Some(self.original.clone())),
+        }
+    }
+
+    fn apply(&mut self) -> Action {
+        let is_divisor_valid = self
+            .input(Field::BreakDivisor)
+            .trim()
+            .parse::<f64>()
+            .is_ok();
+        match Config::parse(&self.doc.to_string()) {
+            Ok(config) if is_divisor_valid => {
+                self.errors.clear();
+                Action::Apply(config)
+            }
+            result => {
+                self.errors = result.err().unwrap_or_default();
+                if !is_divisor_valid {
+                    self.errors.push(ConfigError {
+                        key: "break_divisor".to_string(),
+                        location: None,
+                        message: "must be a number".to_string(),
+                    });
+                }
+                Action::None
+            }
+        }
+    }
+
+    fn save(&self) -> std::io::Result<()> {
+        let path = std::path::Path::new(&self.path);
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        // Renaming onto a symlink would replace it rather than the file it points to.
+        let path = std::fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+        // Write-then-rename so a crash mid-write never leaves a truncated config.
+        let mut tmp = path.clone().into_os_string();
+        tmp.push(".tmp");
+        std::fs::write(&tmp, self.doc.to_string())?;
+        std::fs::rename(tmp, path)
+    }
+
+    pub fn input(&self, field: Field) -> &str {
+        self.inputs.get(&field).map_or("", String::as_str)
+    }
+
+    pub fn flag(&self, field: Field) -> bool {
+        self.flags.get(&field).copied().unwrap_or_default()
+    }
+
+    pub fn colors(&self) -> &'static str {
+        self.colors
+    }
+
+    pub fn error(&self, field: Field) -> Option<&str> {
+        let key = field.path().join(".");
+        self.errors
+            .iter()
+            .find(|err| err.key == key)
+            .map(|err| err.message.as_str())
+    }
+
+    /// Errors that don't belong to any of the shown fields, along with any problem with the file.
+    pub fn other_errors(&self) -> impl Iterator<Item = String> + '_ {
+        let shown = |key: &str| {
+            Field::GENERAL
+                .iter()
+                .any(|field| field.path().join(".") == key)
+                || key.starts_with("color_config.")
+        };
+        self.problem.clone().into_iter().chain(
+            self.errors
+                .iter()
+                .filter(move |err| !shown(&err.key))
+                .map(ToString::to_string),
+        )
+    }
+
+    pub fn can_save(&self) -> bool {
+        self.problem.is_none() && self.errors.is_empty()
+    }
+}
+
+/// Replaces the value at `path`, keeping the comment next to the old one, or removes it if `value` is `None`.
+fn set(item: &mut Item, path: &[&str], value: Option<Value>) {
+    let Some(table) = item.as_table_like_mut() else {
+        return;
+    };
+    match path {
+        [] => {}
+        [key] => match value {
+            Some(mut value) => {
+                if let Some(Item::Value(old)) = table.get_mut(key) {
+                    *value.decor_mut() = old.decor().clone();
+                    *old = value;
+                } else {
+                    table.insert(key, Item::Value(value));
+                }
+            }
+            None => drop(table.remove(key)),
+        },
+        [key, rest @ ..] => {
+            if value.is_none() && !table.contains_key(key) {
+                return;
+            }
+            let is_inline = item.is_inline_table();
+            let Some(table) = item.as_table_like_mut() else {
+                return;
+            };
+            let child = table.entry(key).or_insert_with(|| {
+                if is_inline {
+                    Item::Value(Value::InlineTable(InlineTable::new()))
+                } else {
+                    let mut table = Table::new();
+                    table.set_implicit(true);
+                    Item::Table(table)
+                }
+            });
+            set(child, rest, value);
+        }
+    }
+}
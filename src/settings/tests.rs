@@ -0,0 +1,70 @@
+use super::*;
+
+/// Opens the settings of `content`, written to a file of its own.
+fn open(name: &str, content: &str, config: &Config) -> Settings {
+    let path = std::env::temp_dir().join(format!("fluyendo-{}-{name}.toml", std::process::id()));
+    std::fs::write(&path, content).unwrap();
+    let settings = Settings::open(path.to_str().unwrap(), config);
+    std::fs::remove_file(path).unwrap();
+    settings
+}
+
+#[test]
+fn edits_keep_comments_and_order() {
+    let content = r#"# Longer than usual.
+work_expected_duration = "50m"
+break_divisor = 5 # a fifth
+
+[long_break]
+# Every other one.
+after = 2
+duration = "15m"
+"#;
+    let config = Config::parse(content).unwrap();
+    let mut settings = open("edits", content, &config);
+
+    settings.update(Message::Edit(Field::BreakDivisor, "4".to_string()));
+    settings.update(Message::Edit(
+        Field::WorkExpectedDuration,
+        "45m".to_string(),
+    ));
+    settings.update(Message::Toggle(Field::AutoBreak, true));
+
+    assert_eq!(
+        settings.doc.to_string(),
+        r#"# Longer than usual.
+work_expected_duration = "45m"
+break_divisor = 4.0 # a fifth
+auto_break = true
+
+[long_break]
+# Every other one.
+after = 2
+duration = "15m"
+"#
+    );
+}
+
+#[test]
+fn edits_keep_runtime_mute_and_volume() {
+    let content = "break_divisor = 5\n";
+    let config = Config {
+        mute: true,
+        volume: 0.35,
+        ..Config::parse(content).unwrap()
+    };
+    let mut settings = open("runtime", content, &config);
+    assert!(settings.flag(Field::Mute));
+
+    let Action::Apply(applied) =
+        settings.update(Message::Edit(Field::BreakDivisor, "4".to_string()))
+    else {
+        panic!("the edit was not applied");
+    };
+    assert!(applied.mute);
+    assert_eq!(applied.volume, 0.35);
+    assert_eq!(
+        settings.doc.to_string(),
+        "break_divisor = 4.0\nmute = true\nvolume = 0.35\n"
+    );
+}
@@ -1,14 +1,27 @@
+use std::path::PathBuf;
+use std::time::SystemTime;
+
 use iced::time::Duration;
+use serde::{Deserialize, Serialize};
 
-use crate::config::Config;
+use crate::clock::{Clock, SystemClock};
+use crate::config::{Config, LongBreakBonus, LongBreakConfig};
+use crate::history::{self, IntervalKind, Record};
+use crate::persist::{self, Snapshot};
 use crate::Instant;
 
+#[cfg(test)]
+mod tests;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum PauseKind {
     Work,
     Break,
 }
 
-#[derive(Default)]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum StateKind {
     #[default]
     Begin,
@@ -17,7 +30,7 @@ pub enum StateKind {
     Break,
 }
 
-pub struct State {
+pub struct State<C: Clock = SystemClock> {
     pub kind: StateKind,
 
     // We calculate elapsed manually because the timer might be skewed.
@@ -27,9 +40,22 @@ pub struct State {
 
     pub saved_break_time: Duration,
 
+    pub completed_work_intervals: u32,
+    pub long_break: bool,
+
+    // Wall-clock bookkeeping of the current interval for the history log.
+    pub interval_started_at: Option<SystemTime>,
+    pub interval_paused: bool,
+
     pub work_bound_duration: Duration,
     pub break_divisor: f32,
     pub auto_break: bool,
+    pub long_break_rule: Option<LongBreakConfig>,
+
+    pub snapshot_path: Option<PathBuf>,
+    pub history_path: Option<PathBuf>,
+
+    pub clock: C,
 }
 
 impl StateKind {
@@ -44,31 +70,134 @@ impl StateKind {
 
 impl State {
     pub fn from_config(config: &Config) -> Self {
+        let mut this = Self::with_clock(config, SystemClock);
+        this.snapshot_path = persist::default_path();
+        this.history_path = history::default_path();
+
+        this.restore();
+        this
+    }
+}
+
+impl<C: Clock> State<C> {
+    /// Creates a state that neither restores nor persists anything until paths are set.
+    pub fn with_clock(config: &Config, clock: C) -> Self {
         let mut this = Self {
             kind: StateKind::default(),
             prev_tick: None,
             elapsed: Duration::from_secs(0),
             saved_break_time: Duration::default(),
+            completed_work_intervals: 0,
+            long_break: false,
+            interval_started_at: None,
+            interval_paused: false,
             work_bound_duration: Duration::default(),
             break_divisor: 5.0,
             auto_break: false,
+            long_break_rule: None,
+            snapshot_path: None,
+            history_path: None,
+            clock,
         };
 
         this.update_config(config);
         this
     }
 
+    fn restore(&mut self) {
+        let Some(path) = &self.snapshot_path else {
+            return;
+        };
+        let snapshot = match Snapshot::read(path) {
+            Ok(Some(snapshot)) => snapshot,
+            Ok(None) => return,
+            Err(err) => {
+                eprintln!("failed to read state at {}: {}", path.display(), err);
+                return;
+            }
+        };
+
+        self.kind = snapshot.kind;
+        self.elapsed = snapshot.elapsed;
+        self.saved_break_time = snapshot.saved_break_time;
+        self.completed_work_intervals = snapshot.completed_work_intervals;
+        self.long_break = snapshot.long_break;
+        self.interval_started_at = snapshot.interval_started_at;
+        self.interval_paused = snapshot.interval_paused;
+
+        // The interval kept running while we were gone, so the wall-clock gap counts
+        // towards it as if the window was never closed, but only until the interval ran out.
+        if self.kind.needs_tick() {
+            let gap = self
+                .clock
+                .wall()
+                .duration_since(snapshot.saved_at)
+                .unwrap_or_default();
+            let (elapsed, limit) = self.elapsed_and_limit();
+            self.elapsed = (elapsed + gap).min(limit.max(elapsed));
+            self.prev_tick = Some(self.clock.now());
+        }
+    }
+
+    fn save(&self) {
+        let Some(path) = &self.snapshot_path else {
+            return;
+        };
+        let snapshot = Snapshot {
+            kind: self.kind,
+            elapsed: self.elapsed,
+            saved_break_time: self.saved_break_time,
+            completed_work_intervals: self.completed_work_intervals,
+            long_break: self.long_break,
+            interval_started_at: self.interval_started_at,
+            interval_paused: self.interval_paused,
+            saved_at: self.clock.wall(),
+        };
+        if let Err(err) = snapshot.write(path) {
+            eprintln!("failed to write state at {}: {}", path.display(), err);
+        }
+    }
+
+    fn open_interval(&mut self) {
+        self.interval_started_at = Some(self.clock.wall());
+        self.interval_paused = false;
+    }
+
+    fn close_interval(&mut self, kind: IntervalKind, elapsed: Duration, break_earned: Duration) {
+        let paused = std::mem::take(&mut self.interval_paused);
+        // An interval that was never started (e.g. a skipped break) leaves no record.
+        let Some(start) = self.interval_started_at.take() else {
+            return;
+        };
+        let Some(path) = &self.history_path else {
+            return;
+        };
+        let record = Record {
+            kind,
+            start,
+            end: self.clock.wall(),
+            elapsed,
+            break_earned,
+            paused,
+        };
+        if let Err(err) = history::append(path, &record) {
+            eprintln!("failed to write history at {}: {}", path.display(), err);
+        }
+    }
+
     pub fn update_config(&mut self, config: &Config) {
         let Config {
             work_expected_duration,
             break_divisor,
             auto_break,
+            long_break,
             ..
         } = config;
 
         self.work_bound_duration = *work_expected_duration;
         self.break_divisor = *break_divisor;
         self.auto_break = *auto_break;
+        self.long_break_rule = *long_break;
     }
 
     pub fn kind(&self) -> &StateKind {
@@ -101,12 +230,16 @@ impl State {
     }
 
     pub fn start(&mut self) {
-        self.prev_tick = Some(Instant::now());
+        self.prev_tick = Some(self.clock.now());
         self.kind = match self.kind {
             StateKind::Begin | StateKind::Pause(PauseKind::Work) => StateKind::Work,
             StateKind::Pause(PauseKind::Break) => StateKind::Break,
             StateKind::Work | StateKind::Break => return,
+        };
+        if self.interval_started_at.is_none() {
+            self.open_interval();
         }
+        self.save();
     }
 
     pub fn stop(&mut self) {
@@ -115,23 +248,43 @@ impl State {
             StateKind::Begin => return,
             StateKind::Pause(PauseKind::Work) | StateKind::Work => {
                 let elapsed = std::mem::take(&mut self.elapsed);
-                self.saved_break_time +=
+                let mut break_earned =
                     Duration::from_secs_f32(elapsed.as_secs_f32() / self.break_divisor);
+                if elapsed >= self.work_bound_duration {
+                    self.completed_work_intervals += 1;
+                }
+                if let Some(rule) = self.long_break_rule {
+                    if self.completed_work_intervals >= rule.after {
+                        self.completed_work_intervals = 0;
+                        self.long_break = true;
+                        break_earned = match rule.bonus {
+                            LongBreakBonus::Duration(bonus) => break_earned + bonus,
+                            LongBreakBonus::Multiplier(m) => break_earned.mul_f32(m),
+                        };
+                    }
+                }
+                self.saved_break_time += break_earned;
+                self.close_interval(IntervalKind::Work, elapsed, break_earned);
                 if self.auto_break {
-                    self.prev_tick = Some(Instant::now());
+                    self.prev_tick = Some(self.clock.now());
+                    self.open_interval();
                     StateKind::Break
                 } else {
                     StateKind::Pause(PauseKind::Break)
                 }
             }
             StateKind::Pause(PauseKind::Break) | StateKind::Break => {
+                let elapsed = std::mem::take(&mut self.elapsed);
                 self.saved_break_time = self
                     .saved_break_time
-                    .checked_sub(std::mem::take(&mut self.elapsed))
+                    .checked_sub(elapsed)
                     .unwrap_or_default();
+                self.close_interval(IntervalKind::Break, elapsed, Duration::default());
+                self.long_break = false;
                 StateKind::Pause(PauseKind::Work)
             }
         };
+        self.save();
     }
 
     pub fn pause(&mut self) {
@@ -143,21 +296,37 @@ impl State {
             StateKind::Work => StateKind::Pause(PauseKind::Work),
             StateKind::Break => StateKind::Pause(PauseKind::Break),
         };
+        self.interval_paused = true;
+        self.save();
     }
 
     pub fn name(&self) -> String {
         match self.kind {
             StateKind::Begin => "Ready to start",
             StateKind::Pause(PauseKind::Work) => "Pause (Work)",
+            StateKind::Pause(PauseKind::Break) if self.long_break => "Pause (Long break)",
             StateKind::Pause(PauseKind::Break) => "Pause (Break)",
             StateKind::Work => "Working",
+            StateKind::Break if self.long_break => "Long break",
             StateKind::Break => "Breaking",
         }
         .into()
     }
 
-    pub fn time(&self) -> String {
-        let (elapsed, limit) = match self.kind {
+    pub fn slug(&self) -> &'static str {
+        match self.kind {
+            StateKind::Begin => "begin",
+            StateKind::Pause(PauseKind::Work) => "pause-work",
+            StateKind::Pause(PauseKind::Break) if self.long_break => "pause-long-break",
+            StateKind::Pause(PauseKind::Break) => "pause-break",
+            StateKind::Work => "work",
+            StateKind::Break if self.long_break => "long-break",
+            StateKind::Break => "break",
+        }
+    }
+
+    pub fn elapsed_and_limit(&self) -> (Duration, Duration) {
+        match self.kind {
             StateKind::Begin => (Duration::default(), Duration::default()),
             StateKind::Pause(PauseKind::Work) | StateKind::Work => {
                 (self.elapsed, self.work_bound_duration)
@@ -165,8 +334,11 @@ impl State {
             StateKind::Pause(PauseKind::Break) | StateKind::Break => {
                 (self.elapsed, self.saved_break_time)
             }
-        };
+        }
+    }
 
+    pub fn time(&self) -> String {
+        let (elapsed, limit) = self.elapsed_and_limit();
         let elapsed = duration_to_str(elapsed);
         let limit = duration_to_str(limit);
 
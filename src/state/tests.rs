@@ -0,0 +1,259 @@
+use super::*;
+use crate::clock::FakeClock;
+
+const MINUTE: Duration = Duration::from_secs(60);
+
+fn new_state(config: Config) -> (State<FakeClock>, FakeClock) {
+    let clock = FakeClock::new();
+    (State::with_clock(&config, clock.clone()), clock)
+}
+
+fn run_for(state: &mut State<FakeClock>, clock: &FakeClock, duration: Duration) {
+    clock.advance(duration);
+    state.on_tick_at(clock.now());
+}
+
+#[test]
+fn work_break_work_banks_break_time() {
+    let (mut state, clock) = new_state(Config::default());
+
+    state.start();
+    assert_eq!(state.kind, StateKind::Work);
+    run_for(&mut state, &clock, 25 * MINUTE);
+    assert!(state.is_completed());
+
+    state.stop();
+    assert_eq!(state.kind, StateKind::Break);
+    assert_eq!(state.saved_break_time, 5 * MINUTE);
+    assert_eq!(state.elapsed, Duration::ZERO);
+
+    run_for(&mut state, &clock, 3 * MINUTE);
+    state.stop();
+    assert_eq!(state.kind, StateKind::Pause(PauseKind::Work));
+    assert_eq!(state.saved_break_time, 2 * MINUTE);
+
+    state.start();
+    run_for(&mut state, &clock, 10 * MINUTE);
+    state.stop();
+    assert_eq!(state.saved_break_time, 4 * MINUTE);
+}
+
+#[test]
+fn without_auto_break_break_waits_for_start() {
+    let (mut state, clock) = new_state(Config {
+        auto_break: false,
+        ..Config::default()
+    });
+
+    state.start();
+    run_for(&mut state, &clock, 10 * MINUTE);
+    state.stop();
+    assert_eq!(state.kind, StateKind::Pause(PauseKind::Break));
+    assert_eq!(state.saved_break_time, 2 * MINUTE);
+
+    run_for(&mut state, &clock, 5 * MINUTE);
+    assert_eq!(state.elapsed, Duration::ZERO);
+
+    state.start();
+    assert_eq!(state.kind, StateKind::Break);
+    run_for(&mut state, &clock, MINUTE);
+    assert_eq!(state.elapsed, MINUTE);
+    assert_eq!(state.completed_ratio(), 0.5);
+}
+
+#[test]
+fn pause_stops_the_clock_until_resumed() {
+    let (mut state, clock) = new_state(Config::default());
+
+    state.start();
+    run_for(&mut state, &clock, 5 * MINUTE);
+    state.pause();
+    assert_eq!(state.kind, StateKind::Pause(PauseKind::Work));
+
+    run_for(&mut state, &clock, 10 * MINUTE);
+    assert_eq!(state.elapsed, 5 * MINUTE);
+
+    state.start();
+    assert_eq!(state.kind, StateKind::Work);
+    run_for(&mut state, &clock, 5 * MINUTE);
+    assert_eq!(state.elapsed, 10 * MINUTE);
+    assert_eq!(state.completed_ratio(), 0.4);
+}
+
+#[test]
+fn pausing_a_break_keeps_it_a_break() {
+    let (mut state, clock) = new_state(Config::default());
+
+    state.start();
+    run_for(&mut state, &clock, 25 * MINUTE);
+    state.stop();
+    run_for(&mut state, &clock, 2 * MINUTE);
+    state.pause();
+    assert_eq!(state.kind, StateKind::Pause(PauseKind::Break));
+
+    state.start();
+    assert_eq!(state.kind, StateKind::Break);
+    run_for(&mut state, &clock, MINUTE);
+    state.stop();
+    assert_eq!(state.saved_break_time, 2 * MINUTE);
+}
+
+#[test]
+fn overspent_break_saturates_at_zero() {
+    let (mut state, clock) = new_state(Config::default());
+
+    state.start();
+    run_for(&mut state, &clock, 5 * MINUTE);
+    state.stop();
+    assert_eq!(state.saved_break_time, MINUTE);
+
+    run_for(&mut state, &clock, 3 * MINUTE);
+    assert!(state.is_completed());
+    state.stop();
+    assert_eq!(state.kind, StateKind::Pause(PauseKind::Work));
+    assert_eq!(state.saved_break_time, Duration::ZERO);
+}
+
+#[test]
+fn stop_and_pause_are_ignored_before_start() {
+    let (mut state, _) = new_state(Config::default());
+
+    state.stop();
+    state.pause();
+    assert_eq!(state.kind, StateKind::Begin);
+    assert_eq!(state.saved_break_time, Duration::ZERO);
+}
+
+#[test]
+fn long_break_adds_bonus_after_completed_intervals() {
+    let (mut state, clock) = new_state(Config {
+        long_break: Some(LongBreakConfig {
+            after: 2,
+            bonus: LongBreakBonus::Duration(10 * MINUTE),
+        }),
+        ..Config::default()
+    });
+
+    state.start();
+    run_for(&mut state, &clock, 25 * MINUTE);
+    state.stop();
+    assert!(!state.long_break);
+    run_for(&mut state, &clock, 5 * MINUTE);
+    state.stop();
+
+    state.start();
+    run_for(&mut state, &clock, 25 * MINUTE);
+    state.stop();
+    assert!(state.long_break);
+    assert_eq!(state.name(), "Long break");
+    assert_eq!(state.saved_break_time, 15 * MINUTE);
+
+    state.stop();
+    assert!(!state.long_break);
+}
+
+#[test]
+fn unfinished_work_does_not_count_towards_long_break() {
+    let (mut state, clock) = new_state(Config {
+        long_break: Some(LongBreakConfig {
+            after: 1,
+            bonus: LongBreakBonus::Multiplier(3.0),
+        }),
+        ..Config::default()
+    });
+
+    state.start();
+    run_for(&mut state, &clock, 10 * MINUTE);
+    state.stop();
+    assert!(!state.long_break);
+    assert_eq!(state.saved_break_time, 2 * MINUTE);
+    state.stop();
+
+    state.start();
+    run_for(&mut state, &clock, 25 * MINUTE);
+    state.stop();
+    assert!(state.long_break);
+    assert_eq!(state.saved_break_time, 17 * MINUTE);
+}
+
+#[test]
+fn switching_config_keeps_banked_break() {
+    let (mut state, clock) = new_state(Config::default());
+
+    state.start();
+    run_for(&mut state, &clock, 25 * MINUTE);
+    state.stop();
+    assert_eq!(state.saved_break_time, 5 * MINUTE);
+
+    state.update_config(&Config {
+        work_expected_duration: 50 * MINUTE,
+        break_divisor: 10.0,
+        ..Config::default()
+    });
+    assert_eq!(state.kind, StateKind::Break);
+    assert_eq!(state.saved_break_time, 5 * MINUTE);
+
+    state.stop();
+    state.start();
+    run_for(&mut state, &clock, 50 * MINUTE);
+    assert!(state.is_completed());
+    state.stop();
+    assert_eq!(state.saved_break_time, 10 * MINUTE);
+}
+
+/// A state that saves a snapshot on every change, to a file of its own.
+fn new_saving_state(name: &str) -> (State<FakeClock>, FakeClock) {
+    let (mut state, clock) = new_state(Config::default());
+    let path = std::env::temp_dir().join(format!("fluyendo-{}-{name}.toml", std::process::id()));
+    state.snapshot_path = Some(path);
+    (state, clock)
+}
+
+/// Reads the snapshot of `saved` back after `gap`, as a restart would.
+fn reopen(saved: State<FakeClock>, clock: &FakeClock, gap: Duration) -> State<FakeClock> {
+    clock.advance(gap);
+    let mut state = State::with_clock(&Config::default(), clock.clone());
+    state.snapshot_path = saved.snapshot_path;
+    state.restore();
+    std::fs::remove_file(state.snapshot_path.as_ref().unwrap()).unwrap();
+    state
+}
+
+#[test]
+fn restore_counts_the_gap_while_running() {
+    let (mut state, clock) = new_saving_state("running");
+
+    state.start();
+    let mut state = reopen(state, &clock, 5 * MINUTE);
+    assert_eq!(state.kind, StateKind::Work);
+    assert_eq!(state.elapsed, 5 * MINUTE);
+
+    run_for(&mut state, &clock, MINUTE);
+    assert_eq!(state.elapsed, 6 * MINUTE);
+}
+
+#[test]
+fn restore_caps_the_gap_at_the_end_of_the_interval() {
+    let (mut state, clock) = new_saving_state("weekend");
+
+    state.start();
+    let mut state = reopen(state, &clock, Duration::from_secs(3 * 24 * 60 * 60));
+    assert_eq!(state.elapsed, 25 * MINUTE);
+    assert!(state.is_completed());
+
+    state.snapshot_path = None;
+    state.stop();
+    assert_eq!(state.saved_break_time, 5 * MINUTE);
+}
+
+#[test]
+fn restore_ignores_the_gap_while_paused() {
+    let (mut state, clock) = new_saving_state("paused");
+
+    state.start();
+    run_for(&mut state, &clock, 10 * MINUTE);
+    state.pause();
+    let state = reopen(state, &clock, 60 * MINUTE);
+    assert_eq!(state.kind, StateKind::Pause(PauseKind::Work));
+    assert_eq!(state.elapsed, 10 * MINUTE);
+}
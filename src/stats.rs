@@ -0,0 +1,73 @@
+use std::time::SystemTime;
+
+use chrono::{DateTime, Datelike, Days, Local};
+use iced::time::Duration;
+
+use crate::history::{IntervalKind, Record};
+
+#[cfg(test)]
+mod tests;
+
+#[derive(Default)]
+pub struct Summary {
+    pub focus: Duration,
+    pub sessions: u32,
+    pub break_earned: Duration,
+    pub break_spent: Duration,
+}
+
+pub struct Stats {
+    pub today: Summary,
+    pub week: Summary,
+}
+
+impl Summary {
+    fn add(&mut self, record: &Record) {
+        match record.kind {
+            IntervalKind::Work => {
+                self.focus += record.elapsed;
+                self.sessions += 1;
+                self.break_earned += record.break_earned;
+            }
+            IntervalKind::Break => {
+                self.break_spent += record.elapsed;
+            }
+        }
+    }
+
+    pub fn average_session(&self) -> Duration {
+        if self.sessions == 0 {
+            return Duration::default();
+        }
+        self.focus / self.sessions
+    }
+}
+
+impl Stats {
+    pub fn from_records(records: &[Record], now: SystemTime) -> Self {
+        let now = DateTime::<Local>::from(now);
+        let today_start = now
+            .date_naive()
+            .and_hms_opt(0, 0, 0)
+            .and_then(|midnight| midnight.and_local_timezone(Local).earliest())
+            .unwrap_or(now);
+        let week_start = today_start
+            .checked_sub_days(Days::new(now.weekday().num_days_from_monday().into()))
+            .unwrap_or(today_start);
+        let (today_start, week_start) =
+            (SystemTime::from(today_start), SystemTime::from(week_start));
+
+        let mut today = Summary::default();
+        let mut week = Summary::default();
+        for record in records {
+            if record.start >= today_start {
+                today.add(record);
+            }
+            if record.start >= week_start {
+                week.add(record);
+            }
+        }
+
+        Self { today, week }
+    }
+}
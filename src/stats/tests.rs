@@ -0,0 +1,79 @@
+use chrono::TimeZone;
+
+use super::*;
+
+const MINUTE: Duration = Duration::from_secs(60);
+
+/// A local time in October 2026, when the 14th is a Wednesday. Local so midnights line up with `Stats`.
+fn at(day: u32, hour: u32, min: u32) -> SystemTime {
+    Local
+        .with_ymd_and_hms(2026, 10, day, hour, min, 0)
+        .earliest()
+        .expect("a valid local time")
+        .into()
+}
+
+fn work(start: SystemTime, minutes: u32) -> Record {
+    Record {
+        kind: IntervalKind::Work,
+        start,
+        end: start + minutes * MINUTE,
+        elapsed: minutes * MINUTE,
+        break_earned: minutes * MINUTE / 5,
+        paused: false,
+    }
+}
+
+fn rest(start: SystemTime, minutes: u32) -> Record {
+    Record {
+        kind: IntervalKind::Break,
+        break_earned: Duration::ZERO,
+        ..work(start, minutes)
+    }
+}
+
+#[test]
+fn week_starts_on_monday() {
+    let records = [
+        // Sunday before.
+        work(at(11, 23, 30), 25),
+        // Monday.
+        work(at(12, 0, 30), 20),
+        // Wednesday.
+        work(at(14, 9, 0), 10),
+    ];
+    let stats = Stats::from_records(&records, at(14, 12, 0));
+
+    assert_eq!(stats.week.sessions, 2);
+    assert_eq!(stats.week.focus, 30 * MINUTE);
+    assert_eq!(stats.today.sessions, 1);
+    assert_eq!(stats.today.focus, 10 * MINUTE);
+}
+
+#[test]
+fn a_record_before_midnight_belongs_to_the_day_it_started() {
+    let records = [work(at(13, 23, 59), 25), work(at(14, 0, 0), 25)];
+    let stats = Stats::from_records(&records, at(14, 0, 1));
+
+    assert_eq!(stats.today.sessions, 1);
+    assert_eq!(stats.week.sessions, 2);
+}
+
+#[test]
+fn breaks_count_apart_from_sessions() {
+    let records = [rest(at(14, 9, 0), 5)];
+    let stats = Stats::from_records(&records, at(14, 12, 0));
+
+    assert_eq!(stats.today.sessions, 0);
+    assert_eq!(stats.today.break_spent, 5 * MINUTE);
+    assert_eq!(stats.today.average_session(), Duration::ZERO);
+}
+
+#[test]
+fn average_is_per_session() {
+    let records = [work(at(14, 9, 0), 20), work(at(14, 10, 0), 30)];
+    let stats = Stats::from_records(&records, at(14, 12, 0));
+
+    assert_eq!(stats.today.average_session(), 25 * MINUTE);
+    assert_eq!(stats.today.break_earned, 10 * MINUTE);
+}
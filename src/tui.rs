@@ -0,0 +1,337 @@
+use std::io::Write;
+#[cfg(unix)]
+use std::path::PathBuf;
+#[cfg(unix)]
+use std::sync::atomic::{AtomicI32, Ordering};
+use std::time::Duration;
+
+use anyhow::Result;
+use crossterm::event::{self, KeyCode, KeyEventKind, KeyModifiers};
+use crossterm::style::{Print, SetForegroundColor};
+use crossterm::{cursor, queue, terminal};
+
+use crate::keybindings::{Action, Key, KeyCombo};
+use crate::{App, ButtonKind, Event, Instant};
+
+const FRAME_INTERVAL: Duration = Duration::from_millis(300);
+const RING_RADIUS: f32 = 6.0;
+#[cfg(unix)]
+const LOG_FILE: &str = "headless.log";
+
+/// The real stderr while it is redirected, -1 otherwise.
+#[cfg(unix)]
+static SAVED_STDERR: AtomicI32 = AtomicI32::new(-1);
+
+pub fn run(mut app: App) -> Result<()> {
+    let mut stdout = std::io::stdout();
+    let _terminal = RawTerminal::enter(&mut stdout)?;
+    event_loop(&mut app, &mut stdout)
+}
+
+/// Puts the terminal back however the event loop ends.
+struct RawTerminal {
+    /// Where stderr goes meanwhile.
+    #[cfg(unix)]
+    log: Option<PathBuf>,
+}
+
+impl RawTerminal {
+    fn enter(stdout: &mut std::io::Stdout) -> Result<Self> {
+        // Restored before the panic message is printed, it would be lost on the alternate screen.
+        let tui_thread = std::thread::current().id();
+        let default_hook = std::panic::take_hook();
+        std::panic::set_hook(Box::new(move |info| {
+            if std::thread::current().id() == tui_thread {
+                restore();
+            }
+            default_hook(info);
+        }));
+
+        let terminal = Self {
+            #[cfg(unix)]
+            log: redirect_stderr(),
+        };
+        terminal::enable_raw_mode()?;
+        queue!(stdout, terminal::EnterAlternateScreen, cursor::Hide)?;
+        Ok(terminal)
+    }
+}
+
+impl Drop for RawTerminal {
+    fn drop(&mut self) {
+        restore();
+        #[cfg(unix)]
+        if let Some(log) = &self.log {
+            if std::fs::metadata(log).is_ok_and(|metadata| metadata.len() > 0) {
+                eprintln!("messages of this run were written to {}", log.display());
+            }
+        }
+    }
+}
+
+fn restore() {
+    let mut stdout = std::io::stdout();
+    let _ = queue!(stdout, cursor::Show, terminal::LeaveAlternateScreen);
+    let _ = stdout.flush();
+    let _ = terminal::disable_raw_mode();
+
+    #[cfg(unix)]
+    {
+        let saved = SAVED_STDERR.swap(-1, Ordering::SeqCst);
+        if saved >= 0 {
+            // SAFETY: `saved` is the descriptor duplicated in `redirect_stderr`, owned by us.
+            unsafe {
+                libc::dup2(saved, libc::STDERR_FILENO);
+                libc::close(saved);
+            }
+        }
+    }
+}
+
+/// Sends stderr to a file, the audio thread, notifications and hooks would otherwise
+/// print over the screen.
+#[cfg(unix)]
+fn redirect_stderr() -> Option<PathBuf> {
+    use std::os::fd::AsRawFd;
+
+    let path = crate::dirs::state_dir()?.join(LOG_FILE);
+    let file = path
+        .parent()
+        .map_or(Ok(()), std::fs::create_dir_all)
+        .and_then(|()| std::fs::File::create(&path));
+    let file = match file {
+        Ok(file) => file,
+        Err(err) => {
+            eprintln!("failed to open {}: {}", path.display(), err);
+            return None;
+        }
+    };
+
+    // SAFETY: stderr and `file` are open for the duration of the calls.
+    unsafe {
+        let saved = libc::dup(libc::STDERR_FILENO);
+        if saved < 0 {
+            return None;
+        }
+        if libc::dup2(file.as_raw_fd(), libc::STDERR_FILENO) < 0 {
+            libc::close(saved);
+            return None;
+        }
+        SAVED_STDERR.store(saved, Ordering::SeqCst);
+    }
+    Some(path)
+}
+
+fn event_loop(app: &mut App, stdout: &mut std::io::Stdout) -> Result<()> {
+    #[cfg(unix)]
+    let mut control_events = app.control.as_ref().map(|control| control.events());
+    let mut config_events = crate::watch::config(&app.config_path);
+
+    loop {
+        draw(app, stdout)?;
+
+        let mut events = vec![];
+        if event::poll(FRAME_INTERVAL)? {
+            match event::read()? {
+                event::Event::Key(key) if key.kind == KeyEventKind::Press => {
+                    if key.code == KeyCode::Char('c')
+                        && key.modifiers.contains(KeyModifiers::CONTROL)
+                    {
+                        return Ok(());
+                    }
+                    if let Some(combo) = key_combo(key) {
+                        match app.config.keybindings.action_for(&combo) {
+                            Some(Action::Quit) => return Ok(()),
+                            // There is only the timer to show.
+                            Some(Action::ShowStats | Action::ShowSettings | Action::ShowTimer) => {}
+                            _ => events.push(Event::KeyPressed(combo)),
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+        #[cfg(unix)]
+        if let Some(control_events) = &mut control_events {
+            while let Ok(ev) = control_events.try_recv() {
+                events.push(ev);
+            }
+        }
+        while let Ok(ev) = config_events.try_recv() {
+            events.push(ev);
+        }
+        if app.state.kind.needs_tick() {
+            events.push(Event::TimerTick(Instant::now()));
+        }
+
+        for ev in events {
+            // There is no runtime to drive follow-up tasks, such as notification actions.
+            let _ = app.update(ev);
+        }
+    }
+}
+
+fn key_combo(event: event::KeyEvent) -> Option<KeyCombo> {
+    let key = match event.code {
+        KeyCode::Char(c) => Key::Char(c.to_lowercase().next()?),
+        KeyCode::Esc => Key::Escape,
+        KeyCode::Enter => Key::Enter,
+        KeyCode::Tab | KeyCode::BackTab => Key::Tab,
+        KeyCode::Backspace => Key::Backspace,
+        KeyCode::Delete => Key::Delete,
+        KeyCode::Up => Key::Up,
+        KeyCode::Down => Key::Down,
+        KeyCode::Left => Key::Left,
+        KeyCode::Right => Key::Right,
+        KeyCode::Home => Key::Home,
+        KeyCode::End => Key::End,
+        KeyCode::PageUp => Key::PageUp,
+        KeyCode::PageDown => Key::PageDown,
+        KeyCode::F(n) => Key::F(n),
+        _ => return None,
+    };
+    let modifiers = event.modifiers;
+
+    Some(KeyCombo {
+        ctrl: modifiers.contains(KeyModifiers::CONTROL),
+        alt: modifiers.contains(KeyModifiers::ALT),
+        shift: modifiers.contains(KeyModifiers::SHIFT) || event.code == KeyCode::BackTab,
+        logo: modifiers.contains(KeyModifiers::SUPER),
+        key,
+    })
+}
+
+/// Formats `[key] label` for the first key bound to `action`, or nothing if it is unbound.
+fn hint(app: &App, action: Action, label: &str) -> String {
+    match app.config.keybindings.key_of(action) {
+        Some(key) => format!("[{key}] {label}   "),
+        None => String::new(),
+    }
+}
+
+fn draw(app: &App, stdout: &mut std::io::Stdout) -> Result<()> {
+    let colors = app.color_config();
+    queue!(
+        stdout,
+        terminal::Clear(terminal::ClearType::All),
+        cursor::MoveTo(0, 0),
+        SetForegroundColor(colors.title_text.into()),
+        Print(format!("  {}", app.state.name())),
+    )?;
+    if let Some(profile) = &app.profile {
+        queue!(stdout, Print(format!(" ({profile})")))?;
+    }
+    let volume = if app.config.mute {
+        "muted".to_string()
+    } else {
+        format!("volume {:.0}%", app.config.volume * 100.0)
+    };
+    queue!(stdout, Print(format!("  [{volume}]")))?;
+    queue!(stdout, Print("\r\n\r\n"),)?;
+
+    for line in ring(app.state.completed_ratio()) {
+        queue!(stdout, Print("  "))?;
+        for cell in line {
+            let (symbol, color) = match cell {
+                Cell::Empty => (' ', colors.background),
+                Cell::Filled => ('●', colors.active_circle),
+                Cell::Pending => ('○', colors.pending_circle),
+            };
+            queue!(stdout, SetForegroundColor(color.into()), Print(symbol))?;
+        }
+        queue!(stdout, Print("\r\n"))?;
+    }
+
+    let mut hints = String::new();
+    for button in ButtonKind::all() {
+        if let Some(text) = button.text(app.state.kind()) {
+            hints += &hint(app, button.action(), text);
+        }
+    }
+    let mut more_hints = hint(app, Action::Reload, "Reload")
+        + &hint(app, Action::CancelAudio, "Silence")
+        + &hint(
+            app,
+            Action::ToggleMute,
+            if app.config.mute { "Unmute" } else { "Mute" },
+        )
+        + &hint(app, Action::VolumeUp, "Louder")
+        + &hint(app, Action::VolumeDown, "Quieter");
+    if !app.base_config.profiles.is_empty() {
+        more_hints += &hint(app, Action::NextProfile, "Next profile");
+    }
+    more_hints += &hint(app, Action::Quit, "Quit");
+    queue!(
+        stdout,
+        SetForegroundColor(colors.timer_text.into()),
+        Print(format!("\r\n  {}\r\n\r\n", app.state.time().trim_start())),
+        SetForegroundColor(colors.button_text.into()),
+        Print(format!("  {hints}\r\n")),
+        Print(format!("  {more_hints}\r\n")),
+    )?;
+    if let Some(notice) = app.reload_notice() {
+        queue!(stdout, Print(format!("\r\n  {notice}\r\n")))?;
+    }
+    if !app.config_errors.is_empty() {
+        queue!(
+            stdout,
+            SetForegroundColor(crossterm::style::Color::Red),
+            Print("\r\n")
+        )?;
+        for err in &app.config_errors {
+            queue!(stdout, Print(format!("  {}: {err}\r\n", app.config_path)))?;
+        }
+    }
+    #[cfg(all(not(target_arch = "wasm32"), not(target_env = "musl")))]
+    if let Some(warning) = app.audio.warning() {
+        queue!(
+            stdout,
+            SetForegroundColor(crossterm::style::Color::Red),
+            Print(format!("\r\n  {warning}\r\n"))
+        )?;
+    }
+    stdout.flush()?;
+
+    Ok(())
+}
+
+impl From<crate::color::Color> for crossterm::style::Color {
+    fn from(value: crate::color::Color) -> Self {
+        let channel = |c: f32| (c.clamp(0.0, 1.0) * 255.0).round() as u8;
+        crossterm::style::Color::Rgb {
+            r: channel(value.r),
+            g: channel(value.g),
+            b: channel(value.b),
+        }
+    }
+}
+
+enum Cell {
+    Empty,
+    Filled,
+    Pending,
+}
+
+/// Rasterizes the ring onto a character grid, cells are twice as tall as wide.
+fn ring(ratio: f32) -> Vec<Vec<Cell>> {
+    let r = RING_RADIUS as i32;
+    (-r..=r)
+        .map(|y| {
+            (-2 * r..=2 * r)
+                .map(|x| {
+                    let (x, y) = (x as f32 / 2.0, y as f32);
+                    if ((x * x + y * y).sqrt() - RING_RADIUS).abs() >= 0.5 {
+                        return Cell::Empty;
+                    }
+                    // Same orientation as the canvas ring: from 3 o'clock, clockwise.
+                    let angle = y.atan2(x).to_degrees().rem_euclid(360.0);
+                    if angle < ratio * 360.0 {
+                        Cell::Filled
+                    } else {
+                        Cell::Pending
+                    }
+                })
+                .collect()
+        })
+        .collect()
+}
@@ -0,0 +1,41 @@
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use iced::futures::channel::mpsc;
+use iced::Subscription;
+
+use crate::Event;
+
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Polls the modification time of the config file and asks for a reload once
+/// it stopped changing, so an editor writing in several steps triggers a single reload.
+pub fn config(path: &str) -> mpsc::UnboundedReceiver<Event> {
+    let path = PathBuf::from(path);
+    let (sender, receiver) = mpsc::unbounded();
+    std::thread::spawn(move || {
+        let mut last = fingerprint(&path);
+        let mut changed = false;
+        while !sender.is_closed() {
+            std::thread::sleep(POLL_INTERVAL);
+            let current = fingerprint(&path);
+            if current != last {
+                last = current;
+                changed = true;
+            } else if changed {
+                changed = false;
+                let _ = sender.unbounded_send(Event::Reload);
+            }
+        }
+    });
+    receiver
+}
+
+pub fn subscription(path: &str) -> Subscription<Event> {
+    Subscription::run_with(path.to_string(), |path| config(path))
+}
+
+fn fingerprint(path: &Path) -> Option<(SystemTime, u64)> {
+    let metadata = std::fs::metadata(path).ok()?;
+    Some((metadata.modified().ok()?, metadata.len()))
+}